@@ -0,0 +1,172 @@
+use super::types::box_item::BoxItem;
+use super::types::box_container::BoxContainer;
+use super::types::container::Container;
+use super::placement::Placement;
+use super::space::{FreeSpace, SpaceModel};
+use super::genetic_algorithm::{Evaluation, Evaluator};
+use super::gene;
+use std::sync::Arc;
+
+pub struct PlacedBox {
+  pub item_idx: usize,
+  pub container_idx: usize,
+  pub orientation: i64,
+  pub layer_idx: usize,
+  // Position of the box corner closest to the container origin. Rows go along the depth.
+  pub row: i64,
+  pub col: i64,
+  pub z: i64
+}
+
+#[derive(Clone)]
+pub struct Layer {
+  pub z: i64,
+  pub height: i64
+}
+
+pub struct BoxLayout {
+  pub placed_boxes: Vec<PlacedBox>,
+  // Layers opened in each container, from the bottom up.
+  pub layers: Vec<Vec<Layer>>,
+  pub evaluation: Evaluation
+}
+
+// Places boxes in horizontal layers. A layer is as tall as the first box placed on it, and its
+// floor is packed like a flat container, with the same placement heuristics and space models.
+// Boxes shorter than their layer leave the room above them unused.
+#[derive(Clone)]
+pub struct LayerDecoder {
+  pub placement: Arc<dyn Placement>,
+  pub space_model: SpaceModel
+}
+
+impl LayerDecoder {
+  pub fn new(placement: Arc<dyn Placement>, space_model: SpaceModel) -> LayerDecoder {
+    LayerDecoder { placement, space_model }
+  }
+
+  // Scratch room for the floors of the layers. It grows as layers are opened.
+  pub fn new_spaces(&self, containers: &[BoxContainer]) -> Vec<Vec<Box<dyn FreeSpace>>> {
+    containers.iter().map(|_| Vec::<Box<dyn FreeSpace>>::new()).collect()
+  }
+
+  // Each box goes into the first layer (bottom up) of the first container (in order) where it fits.
+  // A new layer is opened on top when it fits in none of them.
+  pub fn decode(&self, containers: &[BoxContainer], spaces: &mut [Vec<Box<dyn FreeSpace>>], items: &[BoxItem], solution: &[i64]) -> BoxLayout {
+    let mut placed_boxes = Vec::<PlacedBox>::new();
+    let mut layers: Vec<Vec<Layer>> = vec![Vec::<Layer>::new(); containers.len()];
+    let mut container_benefits = vec![0; containers.len()];
+    let mut container_weights = vec![0; containers.len()];
+    let mut missing_required = 0;
+    let mut used: i64 = 0;
+
+    for &item_gene in solution {
+      let item_idx = gene::item_index(item_gene);
+      let item = &items[item_idx];
+      let orientation = gene::orientation(item_gene);
+      let (_, _, height) = item.size(orientation);
+      let footprint = item.footprint(orientation);
+
+      let mut placed = false;
+      for (container_idx, container) in containers.iter().enumerate() {
+        if let Some(max_weight) = container.max_weight {
+          if container_weights[container_idx] + item.weight > max_weight { continue; }
+        }
+
+        let container_layers = &mut layers[container_idx];
+        let container_spaces = &mut spaces[container_idx];
+
+        let mut position = container_layers.iter().enumerate().find_map(|(layer_idx, layer)| {
+          if height > layer.height { return None; }
+          self.placement.find_position(container_spaces[layer_idx].as_ref(), footprint).map(|(row, col)| (layer_idx, row, col))
+        });
+
+        if position.is_none() {
+          let top = container_layers.last().map(|layer| layer.z + layer.height).unwrap_or(0);
+          if top + height > container.height { continue; }
+
+          let layer_idx = container_layers.len();
+          if layer_idx < container_spaces.len() {
+            container_spaces[layer_idx].reset();
+          } else {
+            container_spaces.push(self.space_model.build(&Container::new(container.width, container.depth)));
+          }
+
+          position = self.placement.find_position(container_spaces[layer_idx].as_ref(), footprint).map(|(row, col)| (layer_idx, row, col));
+          if position.is_some() {
+            container_layers.push(Layer { z: top, height });
+          }
+        }
+
+        if let Some((layer_idx, row, col)) = position {
+          container_spaces[layer_idx].fill_shape(row, col, footprint);
+          container_benefits[container_idx] += item.benefit;
+          container_weights[container_idx] += item.weight;
+          used += item.volume();
+
+          let z = container_layers[layer_idx].z;
+          placed_boxes.push(PlacedBox { item_idx, container_idx, orientation, layer_idx, row, col, z });
+          placed = true;
+          break;
+        }
+      }
+
+      if !placed && item.required {
+        missing_required += 1;
+      }
+    }
+
+    BoxLayout {
+      placed_boxes,
+      layers,
+      evaluation: Evaluation {
+        benefit: container_benefits.iter().sum(),
+        wasted: containers.iter().map(|container| container.volume()).sum::<i64>() - used,
+        used,
+        container_benefits,
+        container_weights,
+        missing_required
+      }
+    }
+  }
+}
+
+impl BoxLayout {
+  // Prints the placements grouped by item type, so copies of the same box are listed together.
+  pub fn print_by_item_type(&self, items: &[BoxItem]) {
+    println!("Placements by item type (container:(x, y, z)):");
+    for (item_idx, item) in items.iter().enumerate() {
+      let positions: Vec<String> = self.placed_boxes.iter()
+                                                    .filter(|placed| placed.item_idx == item_idx)
+                                                    .map(|placed| format!("{}:({}, {}, {})", placed.container_idx + 1, placed.col, placed.row, placed.z))
+                                                    .collect();
+
+      println!("#{} {:?} | Placed: {}/{} | {}", item_idx, item, positions.len(), item.quantity, positions.join(" "));
+    }
+  }
+}
+
+// Evaluates solutions for box containers, reusing the same scratch layers.
+pub struct BoxEvaluator {
+  decoder: LayerDecoder,
+  containers: Vec<BoxContainer>,
+  items: Vec<BoxItem>,
+  spaces: Vec<Vec<Box<dyn FreeSpace>>>
+}
+
+impl BoxEvaluator {
+  pub fn new(decoder: LayerDecoder, containers: &[BoxContainer], items: &[BoxItem]) -> BoxEvaluator {
+    BoxEvaluator {
+      spaces: decoder.new_spaces(containers),
+      decoder,
+      containers: containers.to_vec(),
+      items: items.to_vec()
+    }
+  }
+}
+
+impl Evaluator for BoxEvaluator {
+  fn evaluate(&mut self, solution: &[i64]) -> Evaluation {
+    self.decoder.decode(&self.containers, &mut self.spaces, &self.items, solution).evaluation
+  }
+}
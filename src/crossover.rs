@@ -0,0 +1,438 @@
+use super::math;
+use super::gene;
+use rand::seq::SliceRandom;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+// Builds a child from two parents. Both parents contain the same genes, in different orders
+// and possibly with different orientations, and so does the child.
+pub trait Crossover: Send + Sync {
+  fn cross(&self, parent1: &[i64], parent2: &[i64]) -> Vec<i64>;
+}
+
+pub static NAMES: [&str; 6] = ["legacy", "order", "partially-mapped", "cycle", "position-based", "edge-recombination"];
+
+pub fn from_name(name: &str, switch_probability: f32) -> Option<Arc<dyn Crossover>> {
+  match name {
+    "legacy" => Some(Arc::new(Legacy { switch_probability })),
+    "order" => Some(Arc::new(Order)),
+    "partially-mapped" => Some(Arc::new(PartiallyMapped)),
+    "cycle" => Some(Arc::new(Cycle)),
+    "position-based" => Some(Arc::new(PositionBased)),
+    "edge-recombination" => Some(Arc::new(EdgeRecombination)),
+    _ => None
+  }
+}
+
+// Walks both parents at the same time, taking genes from one of them and switching to
+// the other one at random. Genes already taken are skipped.
+pub struct Legacy {
+  // Chance of switching to the other parent after each gene.
+  pub switch_probability: f32
+}
+
+// OX: copies a random segment of the first parent, and fills the rest with the
+// missing genes in the order they have in the second parent, starting after the segment.
+pub struct Order;
+
+// PMX: copies a random segment of the first parent. The genes of the second parent in that
+// segment are moved to the positions given by the mapping between both segments, and the rest
+// of the genes stay where they are in the second parent.
+pub struct PartiallyMapped;
+
+// CX: splits the positions into cycles, and takes the cycles from each parent in turn,
+// so every gene keeps the position it has in one of the parents.
+pub struct Cycle;
+
+// Keeps the genes of the first parent at random positions, and fills the rest with the
+// missing genes in the order they have in the second parent.
+pub struct PositionBased;
+
+// Builds the child from the neighbourhoods of the genes in both parents, following the neighbour
+// that has the fewest neighbours left. Genes keep the orientation they have in the first parent.
+pub struct EdgeRecombination;
+
+// Copies of the same item are told apart by a key: the first copy of an item in the first parent is
+// (item, 0), the second one (item, 1), and so on. In the second parent, each copy gets the key of a copy
+// with the same orientation in the first parent when there's one left, and otherwise of the first copy left.
+// This makes the parents permutations of the same keys, as the classic operators expect, and parents that
+// only differ in the order of their genes give children with the same genes. Orientations are not part of
+// the key, so each gene keeps the orientation it has in the parent it's taken from.
+type Key = (usize, usize);
+
+struct Parent<'a> {
+  genes: &'a [i64],
+  keys: Vec<Key>,
+  positions: HashMap<Key, usize>
+}
+
+impl<'a> Parent<'a> {
+  fn new(genes: &'a [i64], keys: Vec<Key>) -> Parent<'a> {
+    let positions = keys.iter().enumerate().map(|(pos, &key)| (key, pos)).collect();
+    Parent { genes, keys, positions }
+  }
+
+  fn gene(&self, key: Key) -> i64 {
+    self.genes[self.positions[&key]]
+  }
+}
+
+fn parents<'a>(list1: &'a [i64], list2: &'a [i64]) -> (Parent<'a>, Parent<'a>) {
+  let mut occurrences = HashMap::<usize, usize>::new();
+  let keys1: Vec<Key> = list1.iter().map(|&item_gene| {
+    let item_idx = gene::item_index(item_gene);
+    let occurrence = occurrences.entry(item_idx).or_insert(0);
+    *occurrence += 1;
+    (item_idx, *occurrence - 1)
+  }).collect();
+
+  // Same genes first.
+  let mut by_gene = HashMap::<i64, VecDeque<Key>>::new();
+  for (&item_gene, &key) in list1.iter().zip(keys1.iter()) {
+    by_gene.entry(item_gene).or_default().push_back(key);
+  }
+  let mut keys2: Vec<Option<Key>> = list2.iter().map(|item_gene| by_gene.get_mut(item_gene).and_then(VecDeque::pop_front)).collect();
+
+  // Then the copies left, in order.
+  let matched: HashSet<Key> = keys2.iter().flatten().cloned().collect();
+  let mut by_item = HashMap::<usize, VecDeque<Key>>::new();
+  for &key in keys1.iter().filter(|key| !matched.contains(key)) {
+    by_item.entry(key.0).or_default().push_back(key);
+  }
+  for (key, &item_gene) in keys2.iter_mut().zip(list2.iter()) {
+    if key.is_none() {
+      *key = by_item.get_mut(&gene::item_index(item_gene)).and_then(VecDeque::pop_front);
+    }
+  }
+
+  let keys2 = keys2.into_iter().map(|key| key.expect("Lists must contain the same items")).collect();
+  (Parent::new(list1, keys1), Parent::new(list2, keys2))
+}
+
+fn check_lengths(parent1: &[i64], parent2: &[i64]) {
+  if parent1.len() != parent2.len() {
+    panic!("Lists must have the same length");
+  }
+}
+
+// Fills the empty positions of the child, in order, with the given genes.
+fn fill(child: Vec<Option<i64>>, mut rest: impl Iterator<Item = i64>) -> Vec<i64> {
+  child.into_iter().map(|item_gene| item_gene.unwrap_or_else(|| rest.next().unwrap())).collect()
+}
+
+impl Crossover for Legacy {
+  fn cross(&self, list1: &[i64], list2: &[i64]) -> Vec<i64> {
+    check_lengths(list1, list2);
+
+    let mut list: i64 = if math::probability(0.5) { 0 } else { 1 };
+    let mut i = 0;
+    let mut j = 0;
+    let mut result: Vec<i64> = Vec::<i64>::new();
+
+    // Copies of each item that can still be added. Genes are compared by item only,
+    // so the same item isn't added twice with different orientations.
+    let mut remaining = HashMap::<usize, i64>::new();
+    for &val in list1 {
+      *remaining.entry(gene::item_index(val)).or_insert(0) += 1;
+    }
+
+    // list 1 and 2 should be the same size and contain the same items.
+
+    loop {
+      if (i == list1.len()) && (j == list2.len()) {
+        break;
+      }
+
+      // This dummy value can be avoided by ensuring the current list always has items left.
+      let mut val: i64 = -1;
+
+      if list == 0 && i < list1.len() {
+        val = list1[i];
+        i += 1;
+      } else if j < list2.len() {
+        val = list2[j];
+        j += 1;
+      }
+
+      if val != -1 {
+        if let Some(count) = remaining.get_mut(&gene::item_index(val)) {
+          if *count > 0 {
+            result.push(val);
+            *count -= 1;
+          }
+        }
+      }
+
+      // Use different list?
+      if math::probability(self.switch_probability) {
+        list = if list == 0 { 1 } else { 0 };
+      }
+    }
+
+    result
+  }
+}
+
+impl Crossover for Order {
+  fn cross(&self, list1: &[i64], list2: &[i64]) -> Vec<i64> {
+    check_lengths(list1, list2);
+    let len = list1.len();
+    if len == 0 { return Vec::<i64>::new(); }
+
+    let (parent1, parent2) = parents(list1, list2);
+    let (start, end) = math::random_segment(len);
+
+    let mut child = vec![None; len];
+    let mut used = HashSet::<Key>::new();
+    for pos in start..end {
+      child[pos] = Some(list1[pos]);
+      used.insert(parent1.keys[pos]);
+    }
+
+    // Both the child and the second parent are walked from the end of the segment, wrapping around.
+    let rest: Vec<i64> = (0..len).map(|i| (end + i) % len)
+                                 .filter(|&pos| !used.contains(&parent2.keys[pos]))
+                                 .map(|pos| list2[pos])
+                                 .collect();
+    for (i, item_gene) in rest.into_iter().enumerate() {
+      child[(end + i) % len] = Some(item_gene);
+    }
+
+    fill(child, std::iter::empty())
+  }
+}
+
+impl Crossover for PartiallyMapped {
+  fn cross(&self, list1: &[i64], list2: &[i64]) -> Vec<i64> {
+    check_lengths(list1, list2);
+    let (start, end) = math::random_segment(list1.len());
+    self.cross_segment(list1, list2, start, end)
+  }
+}
+
+impl PartiallyMapped {
+  fn cross_segment(&self, list1: &[i64], list2: &[i64], start: usize, end: usize) -> Vec<i64> {
+    let len = list1.len();
+    let (parent1, parent2) = parents(list1, list2);
+
+    let mut child = vec![None; len];
+    let mut used = HashSet::<Key>::new();
+    for pos in start..end {
+      child[pos] = Some(list1[pos]);
+      used.insert(parent1.keys[pos]);
+    }
+
+    for (i, &item_gene) in list2.iter().enumerate().take(end).skip(start) {
+      let key = parent2.keys[i];
+      if used.contains(&key) { continue; }
+
+      // Follows the mapping until it leaves the segment.
+      let mut pos = i;
+      while pos >= start && pos < end {
+        pos = parent2.positions[&parent1.keys[pos]];
+      }
+      child[pos] = Some(item_gene);
+      used.insert(key);
+    }
+
+    // The remaining positions take the gene the second parent has there.
+    child.iter().enumerate().map(|(pos, item_gene)| item_gene.unwrap_or(list2[pos])).collect()
+  }
+}
+
+impl Crossover for Cycle {
+  fn cross(&self, list1: &[i64], list2: &[i64]) -> Vec<i64> {
+    check_lengths(list1, list2);
+    let len = list1.len();
+    let (parent1, parent2) = parents(list1, list2);
+
+    let mut child = vec![None; len];
+    let mut from_first = true;
+    for start in 0..len {
+      if child[start].is_some() { continue; }
+
+      let donor = if from_first { list1 } else { list2 };
+      let mut pos = start;
+      loop {
+        child[pos] = Some(donor[pos]);
+        pos = parent1.positions[&parent2.keys[pos]];
+        if pos == start { break; }
+      }
+      from_first = !from_first;
+    }
+
+    fill(child, std::iter::empty())
+  }
+}
+
+impl Crossover for PositionBased {
+  fn cross(&self, list1: &[i64], list2: &[i64]) -> Vec<i64> {
+    check_lengths(list1, list2);
+    let len = list1.len();
+    let (parent1, parent2) = parents(list1, list2);
+
+    let mut child = vec![None; len];
+    let mut used = HashSet::<Key>::new();
+    for pos in 0..len {
+      if math::probability(0.5) {
+        child[pos] = Some(list1[pos]);
+        used.insert(parent1.keys[pos]);
+      }
+    }
+
+    let rest = (0..len).filter(|&pos| !used.contains(&parent2.keys[pos])).map(|pos| list2[pos]);
+    fill(child, rest)
+  }
+}
+
+impl Crossover for EdgeRecombination {
+  fn cross(&self, list1: &[i64], list2: &[i64]) -> Vec<i64> {
+    check_lengths(list1, list2);
+    let len = list1.len();
+    if len == 0 { return Vec::<i64>::new(); }
+
+    let (parent1, parent2) = parents(list1, list2);
+    let mut rng = math::rng();
+
+    // Solutions are treated as cycles, so the first and last genes are neighbours too.
+    let mut neighbours = HashMap::<Key, Vec<Key>>::new();
+    for parent in &[&parent1, &parent2] {
+      for (pos, &key) in parent.keys.iter().enumerate() {
+        let keys = neighbours.entry(key).or_default();
+        for &neighbour in &[parent.keys[(pos + len - 1) % len], parent.keys[(pos + 1) % len]] {
+          if neighbour != key && !keys.contains(&neighbour) {
+            keys.push(neighbour);
+          }
+        }
+      }
+    }
+
+    let mut remaining: Vec<Key> = parent1.keys.clone();
+    let mut child = Vec::<i64>::new();
+    let mut current = parent1.keys[0];
+
+    loop {
+      child.push(parent1.gene(current));
+      remaining.retain(|&key| key != current);
+      if remaining.is_empty() { break; }
+
+      let current_neighbours = neighbours.remove(&current).unwrap_or_default();
+      for neighbour in &current_neighbours {
+        if let Some(keys) = neighbours.get_mut(neighbour) {
+          keys.retain(|&key| key != current);
+        }
+      }
+
+      // The neighbour with the fewest neighbours left, breaking ties at random.
+      // When there's none, any gene that's not in the child yet.
+      let fewest = current_neighbours.iter().map(|neighbour| neighbours[neighbour].len()).min();
+      let candidates: Vec<Key> = match fewest {
+        Some(fewest) => current_neighbours.into_iter().filter(|neighbour| neighbours[neighbour].len() == fewest).collect(),
+        None => remaining.clone()
+      };
+      current = *candidates.choose(&mut rng).unwrap();
+    }
+
+    child
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::Rng;
+
+  fn genes(items: &[(usize, i64)]) -> Vec<i64> {
+    items.iter().map(|&(item_idx, orientation)| gene::new(item_idx, orientation)).collect()
+  }
+
+  fn sorted(list: &[i64]) -> Vec<i64> {
+    let mut list = list.to_vec();
+    list.sort_unstable();
+    list
+  }
+
+  fn item_indexes(list: &[i64]) -> Vec<usize> {
+    let mut items: Vec<usize> = list.iter().map(|&item_gene| gene::item_index(item_gene)).collect();
+    items.sort_unstable();
+    items
+  }
+
+  // Several copies of some items, with every kind of orientation.
+  fn random_parent() -> Vec<i64> {
+    let mut rng = math::rng();
+    let mut list: Vec<i64> = (0..12).map(|idx| gene::new(idx % 5, rng.gen_range(0..8))).collect();
+    list.shuffle(&mut rng);
+    list
+  }
+
+  // The legacy crossover matches copies by item only, so it's left out.
+  fn crossovers() -> Vec<Arc<dyn Crossover>> {
+    NAMES.iter().filter(|&&name| name != "legacy").map(|name| from_name(name, 0.1).unwrap()).collect()
+  }
+
+  #[test]
+  fn child_is_a_permutation_of_the_parents() {
+    math::seed_rng(1);
+    for crossover in crossovers() {
+      for _ in 0..200 {
+        let parent1 = random_parent();
+        let mut parent2 = parent1.clone();
+        parent2.shuffle(&mut math::rng());
+
+        let child = crossover.cross(&parent1, &parent2);
+        assert_eq!(sorted(&child), sorted(&parent1));
+      }
+    }
+  }
+
+  #[test]
+  fn child_takes_each_orientation_from_a_parent() {
+    math::seed_rng(2);
+    for crossover in crossovers() {
+      for _ in 0..200 {
+        let parent1 = random_parent();
+        // Same items in another order, each copy possibly with another orientation.
+        let mut parent2: Vec<i64> = parent1.iter().map(|&item_gene| gene::with_orientation(item_gene, math::rng().gen_range(0..8))).collect();
+        parent2.shuffle(&mut math::rng());
+
+        let child = crossover.cross(&parent1, &parent2);
+        assert_eq!(item_indexes(&child), item_indexes(&parent1));
+        assert!(child.iter().all(|item_gene| parent1.contains(item_gene) || parent2.contains(item_gene)));
+      }
+    }
+  }
+
+  #[test]
+  fn partially_mapped_known_answer() {
+    let parent1 = genes(&[(1, 0), (2, 0), (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0)]);
+    let parent2 = genes(&[(9, 0), (3, 0), (7, 0), (8, 0), (2, 0), (6, 0), (5, 0), (1, 0), (4, 0)]);
+    let child = PartiallyMapped.cross_segment(&parent1, &parent2, 3, 7);
+    assert_eq!(child, genes(&[(9, 0), (3, 0), (2, 0), (4, 0), (5, 0), (6, 0), (7, 0), (1, 0), (8, 0)]));
+  }
+
+  #[test]
+  fn partially_mapped_keeps_copies_and_orientations() {
+    // Item 0 has two copies. The segment takes the first copy of item 0 and item 1 from the first parent.
+    let parent1 = genes(&[(2, 0), (0, 1), (1, 2), (0, 3)]);
+    let parent2 = genes(&[(1, 4), (0, 5), (2, 6), (0, 7)]);
+    let child = PartiallyMapped.cross_segment(&parent1, &parent2, 1, 3);
+    assert_eq!(child, genes(&[(2, 6), (0, 1), (1, 2), (0, 7)]));
+  }
+
+  #[test]
+  fn cycle_known_answer() {
+    let parent1 = genes(&[(1, 0), (2, 0), (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0)]);
+    let parent2 = genes(&[(8, 0), (5, 0), (2, 0), (1, 0), (3, 0), (6, 0), (4, 0), (7, 0)]);
+    let child = Cycle.cross(&parent1, &parent2);
+    assert_eq!(child, genes(&[(1, 0), (5, 0), (2, 0), (4, 0), (3, 0), (6, 0), (7, 0), (8, 0)]));
+  }
+
+  #[test]
+  fn cycle_keeps_copies_and_orientations() {
+    let parent1 = genes(&[(0, 0), (1, 1), (0, 2), (2, 0)]);
+    let parent2 = genes(&[(1, 0), (0, 1), (0, 3), (2, 2)]);
+    let child = Cycle.cross(&parent1, &parent2);
+    assert_eq!(child, genes(&[(0, 0), (1, 1), (0, 3), (2, 0)]));
+  }
+}
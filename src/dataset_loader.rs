@@ -1,54 +1,626 @@
-use super::types::item::Item;
+use super::types::item::{Item, FixedPosition};
 use super::types::container::Container;
+use super::types::rect::Rect;
+use super::types::shape::Shape;
+use super::types::box_item::BoxItem;
+use super::types::box_container::BoxContainer;
 use super::util;
+use super::math;
+use super::placement::{self, Placement};
+use super::space::{self, SpaceModel};
+use super::decoder::Decoder;
+use super::box_decoder::LayerDecoder;
+use super::genetic_algorithm::GaConfig;
+use super::island::{self, Topology};
+use super::objective;
+use super::scaling;
+use super::selection;
+use super::crossover;
+use super::mutation;
 use clap::{Arg, App, AppSettings, SubCommand};
-use text_io::read;
-use std::io::Read;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
 
 static PROGRAM_DESCRIPTION: &str = "Genetic algorithm for knapsack 2D rectangle allocation.";
 
-fn random_scenario(container_square_size: i64, item_count: i64, item_max_side: i64, item_max_benefit: i64) -> (Container, Vec<Item>) {
-  util::ensure_positive(container_square_size);
-  util::ensure_positive(item_count);
-  util::ensure_positive(item_max_side);
-  util::ensure_positive(item_max_benefit);
+#[allow(clippy::too_many_arguments)]
+fn random_scenario(container_square_sizes: &[i64], container_max_weight: Option<i64>, item_count: i64, item_max_side: i64, item_max_benefit: i64,
+                   rotatable: bool, item_max_quantity: i64, item_max_weight: i64, spacing: i64) -> std::io::Result<(Vec<Container>, Vec<Item>)> {
+  for &container_square_size in container_square_sizes {
+    util::ensure_positive(container_square_size)?;
+  }
+  util::ensure_positive(item_count)?;
+  util::ensure_positive(item_max_side)?;
+  util::ensure_positive(item_max_benefit)?;
+  util::ensure_positive(item_max_quantity)?;
 
-  let container: Container = Container::new(container_square_size, container_square_size);
+  let containers: Vec<Container> = container_square_sizes.iter().map(|&side| {
+    let mut container = Container::new(side, side);
+    container.max_weight = container_max_weight;
+    container
+  }).collect();
   let mut items: Vec::<Item> = Vec::<Item>::new();
 
   for _ in 0..item_count {
-    items.push(Item::make_random(item_max_side, item_max_side, item_max_benefit));
+    let mut item = Item::make_random(item_max_side, item_max_side, item_max_benefit, rotatable, item_max_quantity, item_max_weight);
+    item.spacing = spacing;
+    items.push(item);
+  }
+
+  Ok((containers, items))
+}
+
+fn invalid_data(line_number: usize, message: &str) -> Error {
+  Error::new(ErrorKind::InvalidData, format!("Line {}: {}", line_number, message))
+}
+
+fn parse_number(token: Option<&str>, line_number: usize) -> std::io::Result<i64> {
+  match token {
+    Some(string) => string.parse::<i64>().map_err(|_e| invalid_data(line_number, &format!("'{}' is not a number", string))),
+    None => Err(invalid_data(line_number, "missing value"))
+  }
+}
+
+// Sizes and weights given in the input file must be greater than 0.
+fn parse_positive(token: Option<&str>, line_number: usize, name: &str) -> std::io::Result<i64> {
+  let n = parse_number(token, line_number)?;
+  util::ensure_positive(n).map_err(|_e| invalid_data(line_number, &format!("{} must be greater than 0", name)))
+}
+
+// Shapes are given as rows separated by '/', using '#' for covered cells (e.g. "###/.#./.#.").
+fn parse_shape(value: &str, line_number: usize) -> std::io::Result<Shape> {
+  let cells: Vec<Vec<bool>> = value.split('/').map(|row| row.chars().map(|ch| ch == '#').collect()).collect();
+  let width = cells.iter().map(|row| row.len()).max().unwrap_or(0);
+
+  let empty_row = cells.iter().any(|row| !row.contains(&true));
+  let empty_col = (0..width).any(|col| !cells.iter().any(|row| col < row.len() && row[col]));
+  if empty_row || empty_col {
+    return Err(invalid_data(line_number, "shape must not have empty rows or columns"));
+  }
+
+  Ok(Shape::from_cells(&cells))
+}
+
+// Item lines contain "width height benefit", optionally followed by a quantity and flags.
+// Items without their own "spacing=N" get the default spacing.
+fn parse_item(line: &str, line_number: usize, default_spacing: i64) -> std::io::Result<Item> {
+  let mut tokens = line.split_whitespace().peekable();
+  let w: i64 = parse_positive(tokens.next(), line_number, "width")?;
+  let h: i64 = parse_positive(tokens.next(), line_number, "height")?;
+  let b: i64 = parse_number(tokens.next(), line_number)?;
+  let mut item = Item::new(w, h, b);
+  item.spacing = default_spacing;
+
+  if let Some(Ok(quantity)) = tokens.peek().map(|token| token.parse::<i64>()) {
+    if quantity < 1 {
+      return Err(invalid_data(line_number, "quantity must be greater than 0"));
+    }
+    item.quantity = quantity;
+    tokens.next();
+  }
+
+  for flag in tokens {
+    match flag {
+      "rotatable" => item.rotatable = true,
+      "required" => item.required = true,
+      "mirrorable" => item.mirrorable = true,
+      _ if flag.starts_with("shape=") => {
+        let shape = parse_shape(&flag["shape=".len()..], line_number)?;
+        if shape.width != w || shape.height != h {
+          return Err(invalid_data(line_number, "shape size must match the item width and height"));
+        }
+        item.set_shape(shape);
+      },
+      _ if flag.starts_with("weight=") => {
+        item.weight = parse_positive(Some(&flag["weight=".len()..]), line_number, "weight")?;
+      },
+      _ if flag.starts_with("at=") => {
+        // "at=ROW,COL" for the first container, or "at=ROW,COL,CONTAINER" (counting from 1).
+        let mut values = flag["at=".len()..].split(',');
+        let row = parse_number(values.next(), line_number)?;
+        let col = parse_number(values.next(), line_number)?;
+        let container_number = match values.next() {
+          Some(value) => parse_number(Some(value), line_number)?,
+          None => 1
+        };
+        if container_number < 1 {
+          return Err(invalid_data(line_number, "container numbers start at 1"));
+        }
+        item.fixed = Some(FixedPosition { container_idx: (container_number - 1) as usize, row, col });
+      },
+      _ if flag.starts_with("spacing=") => {
+        let spacing = parse_number(Some(&flag["spacing=".len()..]), line_number)?;
+        if spacing < 0 {
+          return Err(invalid_data(line_number, "spacing must not be negative"));
+        }
+        item.spacing = spacing;
+      },
+      _ => return Err(invalid_data(line_number, &format!("unknown item flag '{}'", flag)))
+    }
+  }
+
+  Ok(item)
+}
+
+// Cells covered by an item that is already placed.
+fn fixed_rects(item: &Item, fixed: &FixedPosition) -> Vec<Rect> {
+  item.shape(0).rects.iter().map(|rect| Rect::new(fixed.row + rect.row, fixed.col + rect.col, rect.width, rect.height)).collect()
+}
+
+// Items that are already placed must be inside their container, and must not overlap
+// obstacles, unusable cells or other placed items (the items before it in the file).
+// They must also keep their spacing from the other placed items (the largest spacing of
+// both items), and from the container border with `border_spacing`.
+fn check_fixed_item(item: &Item, line_number: usize, containers: &[Container], previous_items: &[Item], border_spacing: bool) -> std::io::Result<()> {
+  let fixed = match item.fixed {
+    Some(fixed) => fixed,
+    None => return Ok(())
+  };
+
+  if item.quantity != 1 {
+    return Err(invalid_data(line_number, "items placed with at= must have a quantity of 1"));
+  }
+
+  let container = containers.get(fixed.container_idx).ok_or_else(|| invalid_data(line_number, "no such container"))?;
+  let rects = fixed_rects(item, &fixed);
+
+  if !rects.iter().all(|rect| Rect::new(0, 0, container.width, container.height).contains(rect)) {
+    return Err(invalid_data(line_number, "item must be inside the container"));
+  }
+
+  if border_spacing && item.spacing > 0 {
+    let inner = Rect::new(item.spacing, item.spacing, container.width - 2 * item.spacing, container.height - 2 * item.spacing);
+    if !rects.iter().all(|rect| inner.contains(rect)) {
+      return Err(invalid_data(line_number, "item is closer to the container border than its spacing"));
+    }
+  }
+
+  let mut others: Vec<Rect> = container.blocked();
+  // Cells of each placed item, with the spacing kept between it and this item.
+  let mut spaced = Vec::<(Vec<Rect>, i64)>::new();
+  let mut weight = item.weight;
+  for other in previous_items {
+    if let Some(other_fixed) = other.fixed.filter(|other_fixed| other_fixed.container_idx == fixed.container_idx) {
+      let other_rects = fixed_rects(other, &other_fixed);
+      others.extend(other_rects.iter().cloned());
+      spaced.push((other_rects, std::cmp::max(item.spacing, other.spacing)));
+      weight += other.weight;
+    }
+  }
+
+  if rects.iter().any(|rect| others.iter().any(|other| other.intersects(rect))) {
+    return Err(invalid_data(line_number, "item overlaps an obstacle, unusable cells or another placed item"));
+  }
+
+  let too_close = |(other_rects, spacing): &(Vec<Rect>, i64)| {
+    rects.iter().any(|rect| other_rects.iter().any(|other_rect| other_rect.intersects(&rect.grown(*spacing))))
+  };
+  if spaced.iter().any(too_close) {
+    return Err(invalid_data(line_number, "item is closer to another placed item than their spacing"));
+  }
+
+  if let Some(max_weight) = container.max_weight {
+    if weight > max_weight {
+      return Err(invalid_data(line_number, "placed items exceed the container weight limit"));
+    }
+  }
+
+  Ok(())
+}
+
+// Options after the container size (or mask file). Only "max_weight=N" for now, which is returned if present.
+fn parse_container_options<'a>(tokens: impl Iterator<Item = &'a str>, line_number: usize) -> std::io::Result<Option<i64>> {
+  let mut container_max_weight: Option<i64> = None;
+  for option in tokens {
+    match option {
+      _ if option.starts_with("max_weight=") => {
+        container_max_weight = Some(parse_positive(Some(&option["max_weight=".len()..]), line_number, "max_weight")?);
+      },
+      _ => return Err(invalid_data(line_number, &format!("unexpected value '{}' after the container size", option)))
+    }
+  }
+  Ok(container_max_weight)
+}
+
+// Container lines contain "width height", optionally followed by options.
+fn parse_container(line: &str, line_number: usize) -> std::io::Result<Container> {
+  let mut tokens = line.split_whitespace();
+  let w: i64 = parse_positive(tokens.next(), line_number, "width")?;
+  let h: i64 = parse_positive(tokens.next(), line_number, "height")?;
+
+  let mut container = Container::new(w, h);
+  container.max_weight = parse_container_options(tokens, line_number)?;
+  Ok(container)
+}
+
+// Obstacle lines contain "obstacle row col width height", and belong to the container above them.
+fn parse_obstacle(line: &str, line_number: usize, container: &Container) -> std::io::Result<Rect> {
+  let mut tokens = line.split_whitespace().skip(1);
+  let row: i64 = parse_number(tokens.next(), line_number)?;
+  let col: i64 = parse_number(tokens.next(), line_number)?;
+  let w: i64 = parse_number(tokens.next(), line_number)?;
+  let h: i64 = parse_number(tokens.next(), line_number)?;
+  let obstacle = Rect::new(row, col, w, h);
+
+  if w < 1 || h < 1 || !Rect::new(0, 0, container.width, container.height).contains(&obstacle) {
+    return Err(invalid_data(line_number, "obstacle must be inside the container"));
+  }
+
+  if container.blocked().iter().any(|other| other.intersects(&obstacle)) {
+    return Err(invalid_data(line_number, "obstacles must not overlap each other or cells outside the container"));
+  }
+
+  Ok(obstacle)
+}
+
+// ASCII masks use '#' for usable cells and any other character (e.g. '.') for unusable ones.
+// Images (png, bmp, etc) use light pixels for usable cells and dark pixels for unusable ones.
+fn load_mask(path: &Path) -> std::io::Result<Vec<Vec<bool>>> {
+  let is_text = match path.extension().and_then(|extension| extension.to_str()) {
+    Some(extension) => extension.eq_ignore_ascii_case("txt"),
+    None => true
+  };
+
+  if is_text {
+    let content: String = std::fs::read_to_string(path)?;
+    return Ok(text_mask(&content));
   }
 
-  (container, items)
+  let img = image::open(path).map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?.to_luma();
+  Ok((0..img.height()).map(|y| {
+    (0..img.width()).map(|x| img.get_pixel(x, y).data[0] > 127).collect()
+  }).collect())
 }
 
-fn file_scenario(filename: String) -> std::io::Result<(Container, Vec<Item>)> {
-  let mut file = std::fs::File::open(filename)?.bytes().map(|ch| ch.unwrap());
-  let container_width: i64 = read!("{}", file);
-  let container_height: i64 = read!("{}", file);
-  let item_count: i64 = read!("{}", file);
+// Rows without usable cells (e.g. all spaces) are kept, so the rows below don't move up.
+// Only the empty lines at the end of the file are left out. Short rows are padded with unusable cells.
+fn text_mask(content: &str) -> Vec<Vec<bool>> {
+  let lines: Vec<&str> = content.lines().collect();
+  let row_count = lines.iter().rposition(|line| !line.is_empty()).map_or(0, |idx| idx + 1);
+  let mut mask: Vec<Vec<bool>> = lines[..row_count].iter()
+                                                   .map(|line| line.trim_end().chars().map(|ch| ch == '#').collect())
+                                                   .collect();
+
+  let width = mask.iter().map(|row| row.len()).max().unwrap_or(0);
+  for row in mask.iter_mut() {
+    row.resize(width, false);
+  }
+  mask
+}
+
+// Irregular container lines contain "mask FILE", optionally followed by options. The path is relative to the input file.
+fn parse_mask_container(line: &str, line_number: usize, input_dir: &Path) -> std::io::Result<Container> {
+  let mut tokens = line.split_whitespace().skip(1);
+  let file_name = tokens.next().ok_or_else(|| invalid_data(line_number, "missing mask file"))?;
+  let mask = load_mask(&input_dir.join(file_name))?;
+  let mut container = Container::from_mask(&mask);
+
+  if container.width < 1 || container.height < 1 {
+    return Err(invalid_data(line_number, "mask is empty"));
+  }
+
+  container.max_weight = parse_container_options(tokens, line_number)?;
+  Ok(container)
+}
+
+fn file_scenario(filename: String, spacing: i64, border_spacing: bool) -> std::io::Result<(Vec<Container>, Vec<Item>)> {
+  let content: String = std::fs::read_to_string(&filename)?;
+  let input_dir: &Path = Path::new(&filename).parent().unwrap_or_else(|| Path::new(""));
+
+  // Keep the original line numbers for error messages, but skip empty lines.
+  let mut lines = content.lines()
+                         .enumerate()
+                         .map(|(idx, line)| (idx + 1, line.trim()))
+                         .filter(|(_, line)| !line.is_empty());
+
+  let mut containers: Vec<Container> = Vec::<Container>::new();
+
+  println!("------ Data from file ------");
+
+  // One or more container lines come first. The item count is the first line with a single value.
+  let (line_number, item_count): (usize, i64) = loop {
+    let (line_number, line) = lines.next().ok_or_else(|| invalid_data(1, "missing item count"))?;
+    if line.split_whitespace().count() == 1 {
+      break (line_number, parse_number(Some(line), line_number)?);
+    }
+
+    if line.starts_with("obstacle") {
+      let container = containers.last_mut().ok_or_else(|| invalid_data(line_number, "obstacle before any container"))?;
+      let obstacle = parse_obstacle(line, line_number, container)?;
+      container.obstacles.push(obstacle);
+      continue;
+    }
+
+    if line.starts_with("mask") {
+      containers.push(parse_mask_container(line, line_number, input_dir)?);
+      continue;
+    }
+
+    containers.push(parse_container(line, line_number)?);
+  };
 
-  let container = Container::new(container_width, container_height);
+  if containers.is_empty() {
+    return Err(invalid_data(line_number, "missing container size"));
+  }
+
+  for container in &containers {
+    println!("{:?}", container);
+  }
 
   let mut items: Vec<Item> = Vec::<Item>::new();
 
+  for _ in 0..item_count {
+    let (line_number, line) = lines.next().ok_or_else(|| invalid_data(line_number, "fewer items than the item count"))?;
+    let item = parse_item(line, line_number, spacing)?;
+    check_fixed_item(&item, line_number, &containers, &items, border_spacing)?;
+    println!("{:?}", &item);
+    items.push(item);
+  }
+
+  println!("------ File read finished ------");
+  Ok((containers, items))
+}
+
+// Box container lines contain "width depth height", optionally followed by options.
+fn parse_box_container(line: &str, line_number: usize) -> std::io::Result<BoxContainer> {
+  let mut tokens = line.split_whitespace();
+  let w: i64 = parse_positive(tokens.next(), line_number, "width")?;
+  let d: i64 = parse_positive(tokens.next(), line_number, "depth")?;
+  let h: i64 = parse_positive(tokens.next(), line_number, "height")?;
+
+  let mut container = BoxContainer::new(w, d, h);
+  container.max_weight = parse_container_options(tokens, line_number)?;
+  Ok(container)
+}
+
+// Box lines contain "width depth height benefit", optionally followed by a quantity and flags.
+fn parse_box_item(line: &str, line_number: usize) -> std::io::Result<BoxItem> {
+  let mut tokens = line.split_whitespace().peekable();
+  let w: i64 = parse_positive(tokens.next(), line_number, "width")?;
+  let d: i64 = parse_positive(tokens.next(), line_number, "depth")?;
+  let h: i64 = parse_positive(tokens.next(), line_number, "height")?;
+  let b: i64 = parse_number(tokens.next(), line_number)?;
+  let mut item = BoxItem::new(w, d, h, b);
+
+  if let Some(Ok(quantity)) = tokens.peek().map(|token| token.parse::<i64>()) {
+    if quantity < 1 {
+      return Err(invalid_data(line_number, "quantity must be greater than 0"));
+    }
+    item.quantity = quantity;
+    tokens.next();
+  }
+
+  for flag in tokens {
+    match flag {
+      "rotatable" => item.rotatable = true,
+      "tiltable" => item.tiltable = true,
+      "required" => item.required = true,
+      _ if flag.starts_with("weight=") => {
+        item.weight = parse_positive(Some(&flag["weight=".len()..]), line_number, "weight")?;
+      },
+      _ => return Err(invalid_data(line_number, &format!("unknown box flag '{}'", flag)))
+    }
+  }
+
+  Ok(item)
+}
+
+// Same layout as the flat input file, with box container and box lines instead.
+fn box_file_scenario(filename: String) -> std::io::Result<(Vec<BoxContainer>, Vec<BoxItem>)> {
+  let content: String = std::fs::read_to_string(&filename)?;
+
+  let mut lines = content.lines()
+                         .enumerate()
+                         .map(|(idx, line)| (idx + 1, line.trim()))
+                         .filter(|(_, line)| !line.is_empty());
+
+  let mut containers: Vec<BoxContainer> = Vec::<BoxContainer>::new();
+
   println!("------ Data from file ------");
-  println!("{:?}", container);
+
+  let (line_number, item_count): (usize, i64) = loop {
+    let (line_number, line) = lines.next().ok_or_else(|| invalid_data(1, "missing item count"))?;
+    if line.split_whitespace().count() == 1 {
+      break (line_number, parse_number(Some(line), line_number)?);
+    }
+    containers.push(parse_box_container(line, line_number)?);
+  };
+
+  if containers.is_empty() {
+    return Err(invalid_data(line_number, "missing container size"));
+  }
+
+  for container in &containers {
+    println!("{:?}", container);
+  }
+
+  let mut items: Vec<BoxItem> = Vec::<BoxItem>::new();
 
   for _ in 0..item_count {
-    let w: i64 = read!("{}", file);
-    let h: i64 = read!("{}", file);
-    let b: i64 = read!("{}", file);
-    let item = Item::new(w, h, b);
+    let (line_number, line) = lines.next().ok_or_else(|| invalid_data(line_number, "fewer items than the item count"))?;
+    let item = parse_box_item(line, line_number)?;
     println!("{:?}", &item);
     items.push(item);
   }
 
-  // Files are automatically closed when they go out of scope.
-  // https://doc.rust-lang.org/std/fs/struct.File.html
   println!("------ File read finished ------");
-  Ok((container, items))
+  Ok((containers, items))
+}
+
+// Genetic algorithm parameters, shared by every subcommand. Missing values are taken from GaConfig::default().
+fn ga_args(subcommand: App<'static, 'static>) -> App<'static, 'static> {
+  subcommand
+    .arg(Arg::with_name("population size")
+      .long("population-size")
+      .value_name("SIZE")
+      .takes_value(true)
+      .help("Number of solutions in each generation (default: 100)"))
+    .arg(Arg::with_name("objective")
+      .long("objective")
+      .value_name("OBJECTIVE")
+      .takes_value(true)
+      .possible_values(&objective::NAMES)
+      .default_value("benefit-then-waste")
+      .help("What solutions are ranked by"))
+    .arg(Arg::with_name("waste weight")
+      .long("waste-weight")
+      .value_name("WEIGHT")
+      .takes_value(true)
+      .help("Benefit lost for each unit of wasted room, with the weighted objective (default: 1)"))
+    .arg(Arg::with_name("elite count")
+      .long("elite-count")
+      .value_name("COUNT")
+      .takes_value(true)
+      .help("Best solutions copied into the next generation (default: 10)"))
+    .arg(Arg::with_name("scaling")
+      .long("scaling")
+      .value_name("SCALING")
+      .takes_value(true)
+      .possible_values(&scaling::NAMES)
+      .default_value("none")
+      .help("How the scores are turned into the fitness values used by the selection"))
+    .arg(Arg::with_name("scaling factor")
+      .long("scaling-factor")
+      .value_name("FACTOR")
+      .takes_value(true)
+      .help("Best fitness as a multiple of the average with the linear scaling, standard deviations kept with the sigma scaling, or exponent with the power scaling (default: 2)"))
+    .arg(Arg::with_name("selection")
+      .long("selection")
+      .value_name("SELECTION")
+      .takes_value(true)
+      .possible_values(&selection::NAMES)
+      .default_value("legacy")
+      .help("How the solutions that breed the next generation are picked"))
+    .arg(Arg::with_name("min survivors")
+      .long("min-survivors")
+      .value_name("COUNT")
+      .takes_value(true)
+      .help("Solutions that always survive to breed the next generation, with the legacy selection (default: 10)"))
+    .arg(Arg::with_name("tournament size")
+      .long("tournament-size")
+      .value_name("SIZE")
+      .takes_value(true)
+      .help("Solutions competing in each pick, with the tournament selection (default: 3)"))
+    .arg(Arg::with_name("truncation percent")
+      .long("truncation-percent")
+      .value_name("PERCENT")
+      .takes_value(true)
+      .help("Percentage of the best solutions kept, with the truncation selection (default: 20)"))
+    .arg(Arg::with_name("crossover")
+      .long("crossover")
+      .value_name("CROSSOVER")
+      .takes_value(true)
+      .possible_values(&crossover::NAMES)
+      .default_value("legacy")
+      .help("How each offspring is built from its parents"))
+    .arg(Arg::with_name("crossover switch probability")
+      .long("crossover-switch-probability")
+      .value_name("PROBABILITY")
+      .takes_value(true)
+      .help("Chance of switching to the other parent after each gene, with the legacy crossover (default: 0.1)"))
+    .arg(Arg::with_name("mutation")
+      .long("mutation")
+      .value_name("MUTATION")
+      .takes_value(true)
+      .use_delimiter(true)
+      .possible_values(&mutation::NAMES)
+      .default_value("legacy")
+      .help("How solutions are mutated. Use a comma separated list (e.g. insertion,rotation-flip) to pick one at random for each solution"))
+    .arg(Arg::with_name("mutation skip probability")
+      .long("mutation-skip-probability")
+      .value_name("PROBABILITY")
+      .takes_value(true)
+      .help("Chance of leaving a solution unmutated (default: 0.9)"))
+    .arg(Arg::with_name("gene skip probability")
+      .long("gene-skip-probability")
+      .value_name("PROBABILITY")
+      .takes_value(true)
+      .help("Chance of leaving each gene unchanged when a solution is mutated (default: 0.8)"))
+    .arg(Arg::with_name("keep elites")
+      .long("keep-elites")
+      .help("Copies the elite solutions unmutated into the next generation"))
+    .arg(Arg::with_name("adaptive mutation")
+      .long("adaptive-mutation")
+      .help("Raises the chance of mutating a solution while the optimal score doesn't improve"))
+    .arg(Arg::with_name("pareto")
+      .long("pareto")
+      .help("Maximizes the benefit and minimizes the wasted room (NSGA-II), and outputs the Pareto front"))
+    .arg(Arg::with_name("islands")
+      .long("islands")
+      .value_name("COUNT")
+      .takes_value(true)
+      .help("Populations evolved on separate threads, exchanging their best solutions (default: 1)"))
+    .arg(Arg::with_name("migration interval")
+      .long("migration-interval")
+      .value_name("GENERATIONS")
+      .takes_value(true)
+      .help("Generations between migrations, with several islands (default: 10)"))
+    .arg(Arg::with_name("migrants")
+      .long("migrants")
+      .value_name("COUNT")
+      .takes_value(true)
+      .help("Best solutions each island sends on every migration (default: 2)"))
+    .arg(Arg::with_name("migration topology")
+      .long("migration-topology")
+      .value_name("TOPOLOGY")
+      .takes_value(true)
+      .possible_values(&island::NAMES)
+      .default_value("ring")
+      .help("Where each island sends its migrants"))
+    .arg(Arg::with_name("threads")
+      .long("threads")
+      .value_name("COUNT")
+      .takes_value(true)
+      .help("Threads evaluating each generation, on every island. They're started once, and small generations use fewer of them. The results don't change (default: 1)"))
+    .arg(Arg::with_name("seed")
+      .long("seed")
+      .value_name("SEED")
+      .takes_value(true)
+      .help("Seed for the random numbers, to repeat a run. Runs with several islands may still differ"))
+    .arg(Arg::with_name("cache size")
+      .long("cache-size")
+      .value_name("COUNT")
+      .takes_value(true)
+      .help("Scores of evaluated solutions kept, so they're not evaluated again. 0 disables the cache (default: 10000)"))
+}
+
+// Options shared by every subcommand.
+fn placement_args(subcommand: App<'static, 'static>) -> App<'static, 'static> {
+  subcommand
+    .arg(Arg::with_name("placement")
+      .long("placement")
+      .value_name("PLACEMENT")
+      .takes_value(true)
+      .possible_values(&placement::NAMES)
+      .default_value("top-left")
+      .help("Heuristic used to decide where each item is placed. With --decoder free-rectangles only the corners of the free rectangles are tried, so contact-perimeter and min-waste can place items differently than with the grid"))
+    .arg(Arg::with_name("decoder")
+      .long("decoder")
+      .value_name("DECODER")
+      .takes_value(true)
+      .possible_values(&space::NAMES)
+      .default_value("grid")
+      .help("How the free room is modelled. Use free-rectangles for containers with large dimensions"))
+}
+
+// Options shared by the subcommands for flat containers.
+fn common_args(subcommand: App<'static, 'static>) -> App<'static, 'static> {
+  placement_args(subcommand)
+    .arg(Arg::with_name("spacing")
+      .long("spacing")
+      .value_name("SPACING")
+      .takes_value(true)
+      .default_value("0")
+      .help("Minimum gap between placed items (e.g. the blade kerf). Items can override it with spacing=N"))
+    .arg(Arg::with_name("border spacing")
+      .long("border-spacing")
+      .help("Also keeps the spacing between items and the container border"))
+    .arg(Arg::with_name("min support")
+      .long("min-support")
+      .value_name("FRACTION")
+      .takes_value(true)
+      .help("Stability mode: items must rest on other items or the floor for at least this fraction (0 to 1) of their bottom edge"))
 }
 
 fn file_subcommand() -> App<'static, 'static> {
@@ -60,6 +632,16 @@ fn file_subcommand() -> App<'static, 'static> {
     .required(true))
 }
 
+fn box_subcommand() -> App<'static, 'static> {
+  SubCommand::with_name("box")
+    .about("Packs boxes into pallets, trucks or other 3D containers")
+    .arg(Arg::with_name("file input")
+    .long("file-input")
+    .value_name("FILE")
+    .takes_value(true)
+    .required(true))
+}
+
 fn random_subcommand() -> App<'static, 'static> {
   SubCommand::with_name("random") 
     .arg(Arg::with_name("item count")
@@ -71,7 +653,9 @@ fn random_subcommand() -> App<'static, 'static> {
       .long("container-square-side")
       .value_name("CONTAINER_SQUARE_SIDE")
       .takes_value(true)
-      .required(true))
+      .use_delimiter(true)
+      .required(true)
+      .help("Side of the container. Use a comma separated list (e.g. 20,10,10) for several containers"))
     .arg(Arg::with_name("item max square side")
       .long("item-max-square-side")
       .value_name("ITEM_MAX_SQUARE_SIDE")
@@ -81,32 +665,258 @@ fn random_subcommand() -> App<'static, 'static> {
       .value_name("MAX_BENEFIT")
       .takes_value(true)
       .required(true))
+    .arg(Arg::with_name("max quantity")
+      .long("max-quantity")
+      .value_name("MAX_QUANTITY")
+      .takes_value(true)
+      .default_value("1")
+      .help("Each item gets a random number of identical copies, up to this value"))
+    .arg(Arg::with_name("max weight")
+      .long("max-weight")
+      .value_name("MAX_WEIGHT")
+      .takes_value(true)
+      .default_value("0")
+      .help("Each item gets a random weight, up to this value. Items are weightless by default"))
+    .arg(Arg::with_name("container max weight")
+      .long("container-max-weight")
+      .value_name("CONTAINER_MAX_WEIGHT")
+      .takes_value(true)
+      .help("Payload limit of every container. There's no limit by default"))
+    .arg(Arg::with_name("rotatable")
+      .long("rotatable")
+      .help("Allows every item to be rotated 90 degrees"))
+}
+
+fn placement_from_opts(matches: &clap::ArgMatches) -> (Arc<dyn Placement>, SpaceModel) {
+  let placement = matches.value_of("placement").and_then(placement::from_name);
+  let space_model = matches.value_of("decoder").and_then(SpaceModel::from_name);
+
+  match (placement, space_model) {
+    (Some(placement), Some(space_model)) => (placement, space_model),
+    _ => {
+      // Values have a default and are validated by clap. This case never happens.
+      panic!();
+    }
+  }
+}
+
+fn decoder_from_opts(matches: &clap::ArgMatches) -> std::io::Result<Decoder> {
+  let (placement, space_model) = placement_from_opts(matches);
+
+  let min_support = match matches.value_of("min support") {
+    Some(value) => match value.parse::<f64>() {
+      Ok(fraction) if (0.0..=1.0).contains(&fraction) => Some(fraction),
+      _ => return Err(Error::new(ErrorKind::InvalidInput, "min-support must be a number between 0 and 1"))
+    },
+    None => None
+  };
+
+  Ok(Decoder::new(placement, space_model, matches.is_present("border spacing"), min_support))
+}
+
+fn spacing_from_opts(matches: &clap::ArgMatches) -> std::io::Result<i64> {
+  let spacing = util::parse::<i64>(matches.value_of("spacing"));
+  if spacing < 0 {
+    return Err(Error::new(ErrorKind::InvalidInput, "spacing must not be negative"));
+  }
+  Ok(spacing)
+}
+
+fn probability_from_opts(matches: &clap::ArgMatches, name: &str, default: f32) -> std::io::Result<f32> {
+  match matches.value_of(name) {
+    Some(value) => match value.parse::<f32>() {
+      Ok(probability) if (0.0..=1.0).contains(&probability) => Ok(probability),
+      _ => Err(Error::new(ErrorKind::InvalidInput, format!("{} must be a number between 0 and 1", name)))
+    },
+    None => Ok(default)
+  }
+}
+
+fn ga_config_from_opts(matches: &clap::ArgMatches) -> std::io::Result<GaConfig> {
+  let defaults = GaConfig::default();
+  let count = |name: &str, default: usize| matches.value_of(name).map_or(default, |value| util::parse::<usize>(Some(value)));
+
+  let waste_weight = match matches.value_of("waste weight").map_or(Ok(1.0), |value| value.parse::<f64>()) {
+    Ok(weight) if weight >= 0.0 => weight,
+    _ => return Err(Error::new(ErrorKind::InvalidInput, "waste weight must be a number not less than 0"))
+  };
+  let objective = match matches.value_of("objective").and_then(|name| objective::from_name(name, waste_weight)) {
+    Some(objective) => objective,
+    // The value has a default and is validated by clap. This case never happens.
+    None => panic!()
+  };
+
+  let min_survivors = count("min survivors", 10);
+  let tournament_size = count("tournament size", 3);
+  let truncation_percent = match matches.value_of("truncation percent").map_or(Ok(20.0), |value| value.parse::<f64>()) {
+    Ok(percent) if percent > 0.0 && percent <= 100.0 => percent,
+    _ => return Err(Error::new(ErrorKind::InvalidInput, "truncation percent must be a number greater than 0 and up to 100"))
+  };
+  if min_survivors < 1 || tournament_size < 1 {
+    return Err(Error::new(ErrorKind::InvalidInput, "min survivors and tournament size must be greater than 0"));
+  }
+
+  let selection = match matches.value_of("selection").and_then(|name| selection::from_name(name, min_survivors, tournament_size, truncation_percent)) {
+    Some(selection) => selection,
+    // Same as above.
+    None => panic!()
+  };
+
+  let scaling_name = matches.value_of("scaling").unwrap_or("none");
+  let scaling_factor = match matches.value_of("scaling factor").map_or(Ok(2.0), |value| value.parse::<f64>()) {
+    Ok(factor) if factor > 0.0 && (scaling_name != "linear" || factor > 1.0) => factor,
+    _ => return Err(Error::new(ErrorKind::InvalidInput, "scaling factor must be a number greater than 0 (greater than 1 with the linear scaling)"))
+  };
+  let scaling = match scaling::from_name(scaling_name, scaling_factor) {
+    Some(scaling) => scaling,
+    // Same as above.
+    None => panic!()
+  };
+
+  let switch_probability = probability_from_opts(matches, "crossover switch probability", 0.1)?;
+  let crossover = match matches.value_of("crossover").and_then(|name| crossover::from_name(name, switch_probability)) {
+    Some(crossover) => crossover,
+    // Same as above.
+    None => panic!()
+  };
+
+  let mutations = match matches.values_of("mutation") {
+    Some(names) => names.filter_map(mutation::from_name).collect(),
+    None => defaults.mutations.clone()
+  };
+
+  let config = GaConfig {
+    population_size: count("population size", defaults.population_size),
+    objective,
+    elite_count: count("elite count", defaults.elite_count),
+    scaling,
+    selection,
+    crossover,
+    mutations,
+    mutation_skip_probability: probability_from_opts(matches, "mutation skip probability", defaults.mutation_skip_probability)?,
+    gene_skip_probability: probability_from_opts(matches, "gene skip probability", defaults.gene_skip_probability)?,
+    mutate_elites: !matches.is_present("keep elites"),
+    adaptive_mutation: matches.is_present("adaptive mutation"),
+    pareto: matches.is_present("pareto"),
+    islands: count("islands", defaults.islands),
+    migration_interval: count("migration interval", defaults.migration_interval as usize) as i64,
+    migrants: count("migrants", defaults.migrants),
+    migration_topology: matches.value_of("migration topology").and_then(Topology::from_name).unwrap_or(defaults.migration_topology),
+    threads: count("threads", defaults.threads),
+    seed: matches.value_of("seed").map(|value| util::parse::<u64>(Some(value))),
+    cache_size: count("cache size", defaults.cache_size)
+  };
+
+  if config.population_size < 1 || config.islands < 1 || config.migration_interval < 1 || config.threads < 1 {
+    return Err(Error::new(ErrorKind::InvalidInput, "population size, islands, migration interval and threads must be greater than 0"));
+  }
+  if config.elite_count > config.population_size {
+    return Err(Error::new(ErrorKind::InvalidInput, "elite count must not be greater than the population size"));
+  }
+
+  Ok(config)
+}
+
+// Flat items go into flat containers, and boxes into box containers.
+pub enum Scenario {
+  Flat(Vec<Container>, Vec<Item>, Decoder),
+  Boxes(Vec<BoxContainer>, Vec<BoxItem>, LayerDecoder)
 }
 
-pub fn build_scenario_from_opts() -> std::io::Result<(Container, Vec<Item>)> {
+pub fn build_scenario_from_opts() -> std::io::Result<(Scenario, GaConfig)> {
   let matches = App::new(PROGRAM_DESCRIPTION).setting(AppSettings::SubcommandRequiredElseHelp)
-                                             .subcommand(file_subcommand())
-                                             .subcommand(random_subcommand())
+                                             .subcommand(ga_args(common_args(file_subcommand())))
+                                             .subcommand(ga_args(common_args(random_subcommand())))
+                                             .subcommand(ga_args(placement_args(box_subcommand())))
                                              .get_matches();
 
-  match matches.subcommand() {
+  let ga_config = match matches.subcommand() {
+    (_, Some(matches)) => ga_config_from_opts(matches)?,
+    _ => GaConfig::default()
+  };
+
+  // Seeded before the random scenario and the first generation are built, so both are repeated too.
+  if let Some(seed) = ga_config.seed {
+    math::seed_rng(seed);
+  }
+
+  let scenario = match matches.subcommand() {
     ("random", Some(matches)) => {
-      Ok(random_scenario(
-        util::parse::<i64>(matches.value_of("container square side")),
+      let container_sides: Vec<i64> = match matches.values_of("container square side") {
+        Some(values) => values.map(|value| util::parse::<i64>(Some(value))).collect(),
+        None => { panic!() }
+      };
+
+      let (containers, items) = random_scenario(
+        &container_sides,
+        matches.value_of("container max weight").map(|value| util::parse::<i64>(Some(value))),
         util::parse::<i64>(matches.value_of("item count")),
         util::parse::<i64>(matches.value_of("item max square side")),
-        util::parse::<i64>(matches.value_of("max benefit"))
-      ))
+        util::parse::<i64>(matches.value_of("max benefit")),
+        matches.is_present("rotatable"),
+        util::parse::<i64>(matches.value_of("max quantity")),
+        util::parse::<i64>(matches.value_of("max weight")),
+        spacing_from_opts(matches)?
+      )?;
+      Scenario::Flat(containers, items, decoder_from_opts(matches)?)
     },
     ("file", Some(matches)) => {
       let file_name = match matches.value_of("file input") {
         Some(string) => string,
         None => { panic!() }
       };
-      file_scenario(file_name.to_string())
+      let (containers, items) = file_scenario(file_name.to_string(), spacing_from_opts(matches)?, matches.is_present("border spacing"))?;
+      Scenario::Flat(containers, items, decoder_from_opts(matches)?)
+    },
+    ("box", Some(matches)) => {
+      let file_name = match matches.value_of("file input") {
+        Some(string) => string,
+        None => { panic!() }
+      };
+      let (containers, items) = box_file_scenario(file_name.to_string())?;
+      let (placement, space_model) = placement_from_opts(matches);
+      Scenario::Boxes(containers, items, LayerDecoder::new(placement, space_model))
     },
     _ => {
       panic!();
     }
+  };
+
+  Ok((scenario, ga_config))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn text_mask_keeps_rows_without_usable_cells() {
+    let mask = text_mask("###\n   \n.#\n\n\n");
+    assert_eq!(mask, vec![vec![true, true, true], vec![false, false, false], vec![false, true, false]]);
+
+    let container = Container::from_mask(&mask);
+    assert_eq!((container.width, container.height), (3, 3));
+  }
+
+  #[test]
+  fn sizes_must_be_positive() {
+    assert!(parse_item("0 3 5", 1, 0).is_err());
+    assert!(parse_item("2 -3 5", 1, 0).is_err());
+    assert!(parse_item("2 3 0", 1, 0).is_ok());
+    assert!(parse_container("4 0", 1).is_err());
+    assert!(parse_container("-4 4", 1).is_err());
+    assert!(parse_box_container("4 0 4", 1).is_err());
+    assert!(parse_box_item("1 1 -1 5", 1).is_err());
+  }
+
+  #[test]
+  fn weights_must_be_positive() {
+    assert!(parse_item("2 3 5 weight=0", 1, 0).is_err());
+    assert!(parse_item("2 3 5 weight=-1", 1, 0).is_err());
+    assert_eq!(parse_item("2 3 5 weight=4", 1, 0).unwrap().weight, 4);
+    assert!(parse_container("4 4 max_weight=0", 1).is_err());
+    assert_eq!(parse_container("4 4 max_weight=9", 1).unwrap().max_weight, Some(9));
+    assert!(parse_box_container("4 4 4 max_weight=-2", 1).is_err());
+    assert!(parse_box_item("1 1 1 5 weight=0", 1).is_err());
   }
 }
@@ -0,0 +1,337 @@
+use super::types::item::Item;
+use super::types::container::Container;
+use super::types::rect::Rect;
+use super::types::shape::Shape;
+use super::placement::Placement;
+use super::space::{FreeSpace, SpaceModel};
+use super::genetic_algorithm::{Evaluation, Evaluator};
+use super::gene;
+use std::sync::Arc;
+
+pub struct PlacedItem {
+  pub item_idx: usize,
+  pub container_idx: usize,
+  pub orientation: i64,
+  pub row: i64,
+  pub col: i64
+}
+
+pub struct Layout {
+  pub placed_items: Vec<PlacedItem>,
+  pub evaluation: Evaluation
+}
+
+// How solutions are turned into layouts: where each item goes, and how the free room is modelled.
+#[derive(Clone)]
+pub struct Decoder {
+  pub placement: Arc<dyn Placement>,
+  pub space_model: SpaceModel,
+  // Items with spacing also keep it clear from the container border.
+  pub border_spacing: bool,
+  // Stability mode: minimum fraction of each item's bottom edge that must rest on
+  // other items, obstacles or the container floor. Positions are then scanned bottom-up.
+  pub min_support: Option<f64>
+}
+
+// The cells around a placed item that must stay clear, i.e. its rectangle grown by the
+// item's spacing, without the part that falls outside the container.
+pub fn spacing_margin(rect: &Rect, spacing: i64, container_width: i64, container_height: i64) -> Option<Rect> {
+  rect.grown(spacing).clipped(&Rect::new(0, 0, container_width, container_height))
+}
+
+// Wraps a container's space so the item being placed follows the decoder rules.
+// With spacing, the item keeps its gap from other items. Placed items fill their spacing
+// margin too, so the next items keep clear of it without any extra check. The actual cells of
+// the placed items are kept in `placed`, for items that need a larger gap than the items already placed.
+// With a minimum support, the item must rest on something, so positions are scanned bottom-up.
+struct ConstrainedSpace<'a> {
+  space: &'a mut dyn FreeSpace,
+  placed: &'a mut Vec<Rect>,
+  spacing: i64,
+  border_spacing: bool,
+  min_support: Option<f64>,
+  // Obstacles and unusable cells of the container. Only given when spacing margins are filled in
+  // the space: margins are empty room, so the support is counted from `placed` and these instead.
+  blocked: Option<&'a [Rect]>
+}
+
+impl<'a> ConstrainedSpace<'a> {
+  fn allows(&self, row: i64, col: i64, shape: &Shape) -> bool {
+    self.keeps_spacing(row, col, shape) && self.is_supported(row, col, shape)
+  }
+
+  fn is_supported(&self, row: i64, col: i64, shape: &Shape) -> bool {
+    match self.min_support {
+      Some(min_support) => self.supported_fraction(row, col, shape) >= min_support,
+      None => true
+    }
+  }
+
+  fn supported_fraction(&self, row: i64, col: i64, shape: &Shape) -> f64 {
+    let blocked = match self.blocked {
+      Some(blocked) => blocked,
+      None => return self.space.shape_supported_fraction(row, col, shape)
+    };
+
+    let (_, height) = self.space.size();
+    let bottom = shape.bottom_profile();
+    let supported: i64 = bottom.iter().map(|segment| {
+      let below = row + segment.row + 1;
+      if below >= height {
+        segment.width
+      } else {
+        Rect::covered_cells_in_row(self.placed.iter().chain(blocked.iter()), below, col + segment.col, segment.width)
+      }
+    }).sum();
+    let total: i64 = bottom.iter().map(|segment| segment.width).sum();
+    supported as f64 / total as f64
+  }
+
+  fn keeps_spacing(&self, row: i64, col: i64, shape: &Shape) -> bool {
+    if self.spacing == 0 {
+      return true;
+    }
+
+    let (width, height) = self.space.size();
+    if self.border_spacing && (row < self.spacing || col < self.spacing ||
+                               row + shape.height + self.spacing > height || col + shape.width + self.spacing > width) {
+      return false;
+    }
+
+    shape.rects.iter().all(|rect| {
+      let margin = Rect::new(row + rect.row, col + rect.col, rect.width, rect.height).grown(self.spacing);
+      !self.placed.iter().any(|placed| placed.intersects(&margin))
+    })
+  }
+}
+
+impl<'a> FreeSpace for ConstrainedSpace<'a> {
+  fn reset(&mut self) {
+    self.space.reset();
+    self.placed.clear();
+  }
+
+  fn size(&self) -> (i64, i64) {
+    self.space.size()
+  }
+
+  fn fits(&self, row: i64, col: i64, width: i64, height: i64) -> bool {
+    self.space.fits(row, col, width, height)
+  }
+
+  fn fill(&mut self, row: i64, col: i64, width: i64, height: i64) {
+    let rect = Rect::new(row, col, width, height);
+    let (container_width, container_height) = self.space.size();
+    if let Some(margin) = spacing_margin(&rect, self.spacing, container_width, container_height) {
+      self.space.fill(margin.row, margin.col, margin.width, margin.height);
+    }
+    self.placed.push(rect);
+  }
+
+  fn count_free(&self) -> i64 {
+    self.space.count_free()
+  }
+
+  fn visit_positions(&self, shape: &Shape, bottom_up: bool, visit: &mut dyn FnMut(i64, i64) -> bool) {
+    let bottom_up = bottom_up || self.min_support.is_some();
+    self.space.visit_spaced_positions(shape, bottom_up, self.spacing, &mut |row, col| {
+      !self.allows(row, col, shape) || visit(row, col)
+    });
+  }
+
+  fn contact_perimeter(&self, row: i64, col: i64, width: i64, height: i64) -> i64 {
+    self.space.contact_perimeter(row, col, width, height)
+  }
+
+  fn trapped_cells_above(&self, row: i64, col: i64, width: i64) -> i64 {
+    self.space.trapped_cells_above(row, col, width)
+  }
+
+  fn filled_cells_below(&self, row: i64, col: i64, width: i64) -> i64 {
+    self.space.filled_cells_below(row, col, width)
+  }
+}
+
+impl Decoder {
+  pub fn new(placement: Arc<dyn Placement>, space_model: SpaceModel, border_spacing: bool, min_support: Option<f64>) -> Decoder {
+    Decoder { placement, space_model, border_spacing, min_support }
+  }
+
+  fn constrain<'a>(&self, space: &'a mut dyn FreeSpace, placed: &'a mut Vec<Rect>, item: &Item, blocked: Option<&'a [Rect]>) -> ConstrainedSpace<'a> {
+    ConstrainedSpace { space, placed, spacing: item.spacing, border_spacing: self.border_spacing, min_support: self.min_support, blocked }
+  }
+
+  // One space per container.
+  pub fn new_spaces(&self, containers: &[Container]) -> Vec<Box<dyn FreeSpace>> {
+    containers.iter().map(|container| self.space_model.build(container)).collect()
+  }
+
+  // Turns a solution into actual item positions. Both the score and the image are
+  // computed from this, so they always agree.
+  // Each item goes into the first container (in order) where it fits, without exceeding its weight limit.
+  // The spaces are only scratch room, and are reused between calls to avoid allocating them every time.
+  pub fn decode(&self, containers: &[Container], spaces: &mut [Box<dyn FreeSpace>], items: &[Item], solution: &[i64]) -> Layout {
+    for space in spaces.iter_mut() {
+      space.reset();
+    }
+
+    // Gaps left by the spacing are wasted room, so it's counted from the item areas
+    // rather than from the free cells left in the spaces.
+    let usable: i64 = spaces.iter().map(|space| space.count_free()).sum();
+    let mut used: i64 = 0;
+
+    let mut placed_items = Vec::<PlacedItem>::new();
+    let mut placed_rects = vec![Vec::<Rect>::new(); spaces.len()];
+    let mut container_benefits = vec![0; spaces.len()];
+    let mut container_weights = vec![0; spaces.len()];
+    let mut missing_required = 0;
+
+    // Without spacing, the filled cells of the spaces are exactly the items and obstacles, so they give the support.
+    let blocked: Vec<Vec<Rect>> = if self.min_support.is_some() && items.iter().any(|item| item.spacing > 0) {
+      containers.iter().map(|container| container.blocked()).collect()
+    } else {
+      Vec::new()
+    };
+
+    // Items that are already placed go first, so the rest are placed around them.
+    for (item_idx, item) in items.iter().enumerate() {
+      if let Some(fixed) = item.fixed {
+        let container_idx = fixed.container_idx;
+        let shape = item.shape(0);
+        let mut space = self.constrain(spaces[container_idx].as_mut(), &mut placed_rects[container_idx], item, blocked.get(container_idx).map(Vec::as_slice));
+        space.fill_shape(fixed.row, fixed.col, shape);
+
+        container_benefits[container_idx] += item.benefit;
+        container_weights[container_idx] += item.weight;
+        used += shape.area();
+        placed_items.push(PlacedItem { item_idx, container_idx, orientation: 0, row: fixed.row, col: fixed.col });
+      }
+    }
+
+    // Place every item until there's no room left.
+    for &item_gene in solution {
+      let item_idx = gene::item_index(item_gene);
+      let item = &items[item_idx];
+      let orientation = gene::orientation(item_gene);
+      let shape = item.shape(orientation);
+
+      // If we stop at the first item that doesn't fit, then the allocating heuristic changes.
+      // What's important is to keep the heuristic the same during the program execution
+      // (i.e. don't use different heuristics for different things).
+      let mut placed = false;
+      for (container_idx, (space, rects)) in spaces.iter_mut().zip(placed_rects.iter_mut()).enumerate() {
+        if let Some(max_weight) = containers[container_idx].max_weight {
+          if container_weights[container_idx] + item.weight > max_weight { continue; }
+        }
+
+        let mut space = self.constrain(space.as_mut(), rects, item, blocked.get(container_idx).map(Vec::as_slice));
+        if let Some((row, col)) = self.placement.find_position(&space, shape) {
+          container_benefits[container_idx] += item.benefit;
+          container_weights[container_idx] += item.weight;
+          used += shape.area();
+          space.fill_shape(row, col, shape);
+
+          placed_items.push(PlacedItem { item_idx, container_idx, orientation, row, col });
+          placed = true;
+          break;
+        }
+      }
+
+      if !placed && item.required {
+        missing_required += 1;
+      }
+    }
+
+    Layout {
+      placed_items,
+      evaluation: Evaluation {
+        benefit: container_benefits.iter().sum(),
+        wasted: usable - used,
+        used,
+        container_benefits,
+        container_weights,
+        missing_required
+      }
+    }
+  }
+}
+
+// Evaluates solutions for flat containers, reusing the same scratch spaces (one per container).
+pub struct LayoutEvaluator {
+  decoder: Decoder,
+  containers: Vec<Container>,
+  items: Vec<Item>,
+  spaces: Vec<Box<dyn FreeSpace>>
+}
+
+impl LayoutEvaluator {
+  pub fn new(decoder: Decoder, containers: &[Container], items: &[Item]) -> LayoutEvaluator {
+    LayoutEvaluator {
+      spaces: decoder.new_spaces(containers),
+      decoder,
+      containers: containers.to_vec(),
+      items: items.to_vec()
+    }
+  }
+}
+
+impl Evaluator for LayoutEvaluator {
+  fn evaluate(&mut self, solution: &[i64]) -> Evaluation {
+    self.decoder.decode(&self.containers, &mut self.spaces, &self.items, solution).evaluation
+  }
+}
+
+impl Layout {
+  // Prints the placements grouped by item type, so copies of the same item are listed together.
+  pub fn print_by_item_type(&self, items: &[Item]) {
+    println!("Placements by item type (container:(row, col)):");
+    for (item_idx, item) in items.iter().enumerate() {
+      let positions: Vec<String> = self.placed_items.iter()
+                                                    .filter(|placed| placed.item_idx == item_idx)
+                                                    .map(|placed| format!("{}:({}, {})", placed.container_idx + 1, placed.row, placed.col))
+                                                    .collect();
+
+      println!("#{} {:?} | Placed: {}/{} | {}", item_idx, item, positions.len(), item.quantity, positions.join(" "));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::placement;
+
+  fn decode(space_model: SpaceModel, border_spacing: bool, items: &[Item]) -> Layout {
+    let decoder = Decoder::new(placement::from_name("top-left").unwrap(), space_model, border_spacing, None);
+    let containers = vec![Container::new(10, 8)];
+    let mut spaces = decoder.new_spaces(&containers);
+    let solution: Vec<i64> = items.iter().enumerate().map(|(item_idx, _)| gene::new(item_idx, 0)).collect();
+    decoder.decode(&containers, &mut spaces, items, &solution)
+  }
+
+  fn spaced_item(width: i64, height: i64, spacing: i64) -> Item {
+    let mut item = Item::new(width, height, 1);
+    item.spacing = spacing;
+    item
+  }
+
+  fn positions(layout: &Layout) -> Vec<(i64, i64)> {
+    layout.placed_items.iter().map(|placed| (placed.row, placed.col)).collect()
+  }
+
+  #[test]
+  fn free_rectangles_keep_border_spacing() {
+    let items = vec![spaced_item(3, 3, 1), spaced_item(2, 2, 1)];
+    let layout = decode(SpaceModel::FreeRectangles, true, &items);
+    assert_eq!(positions(&layout), vec![(1, 1), (1, 5)]);
+    assert_eq!(positions(&layout), positions(&decode(SpaceModel::Grid, true, &items)));
+  }
+
+  #[test]
+  fn free_rectangles_keep_the_largest_spacing() {
+    // The second item needs a larger gap than the margin left by the first one.
+    let items = vec![spaced_item(3, 3, 0), spaced_item(2, 2, 2)];
+    let layout = decode(SpaceModel::FreeRectangles, false, &items);
+    assert_eq!(positions(&layout), vec![(0, 0), (0, 5)]);
+  }
+}
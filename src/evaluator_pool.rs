@@ -0,0 +1,88 @@
+use super::genetic_algorithm::{Evaluation, Evaluator};
+use crossbeam::channel::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+
+// Fewest solutions worth sending to another thread. Smaller batches are split between fewer threads.
+static MIN_CHUNK_SIZE: usize = 8;
+
+// Evaluators that keep running on their own threads for the whole run, so each generation
+// only sends them solutions instead of starting new threads.
+// The first evaluator stays on the calling thread, and evaluates its share of the solutions too.
+pub struct EvaluatorPool {
+  local: Box<dyn Evaluator>,
+  workers: Vec<Worker>
+}
+
+struct Worker {
+  // None once the pool is dropped, which stops the thread.
+  jobs: Option<Sender<Vec<Vec<i64>>>>,
+  results: Receiver<Vec<Evaluation>>,
+  thread: Option<JoinHandle<()>>
+}
+
+impl Worker {
+  fn new(mut evaluator: Box<dyn Evaluator>) -> Worker {
+    let (jobs, job_receiver) = channel::unbounded::<Vec<Vec<i64>>>();
+    let (result_sender, results) = channel::unbounded();
+
+    let thread = std::thread::spawn(move || {
+      for solutions in job_receiver {
+        let evaluations: Vec<Evaluation> = solutions.iter().map(|solution| evaluator.evaluate(solution)).collect();
+        if result_sender.send(evaluations).is_err() {
+          break;
+        }
+      }
+    });
+
+    Worker { jobs: Some(jobs), results, thread: Some(thread) }
+  }
+}
+
+impl EvaluatorPool {
+  pub fn new(evaluators: Vec<Box<dyn Evaluator>>) -> EvaluatorPool {
+    let mut evaluators = evaluators.into_iter();
+    let local = evaluators.next().expect("at least one evaluator is needed");
+    EvaluatorPool { local, workers: evaluators.map(Worker::new).collect() }
+  }
+
+  // The evaluator on the calling thread.
+  pub fn local(&mut self) -> &mut dyn Evaluator {
+    self.local.as_mut()
+  }
+
+  // Evaluates the solutions, returning the evaluations in the same order.
+  // Evaluators don't use random numbers, so the results are the same as with a single evaluator.
+  pub fn evaluate_all(&mut self, solutions: &[&[i64]]) -> Vec<Evaluation> {
+    let threads = self.workers.len() + 1;
+    let chunk_size = std::cmp::max(MIN_CHUNK_SIZE, solutions.len() / threads + 1);
+    if self.workers.is_empty() || solutions.len() <= chunk_size {
+      return solutions.iter().map(|solution| self.local.evaluate(solution)).collect();
+    }
+
+    let mut chunks = solutions.chunks(chunk_size);
+    let local_chunk = chunks.next().unwrap_or(&[]);
+    let busy: Vec<&Worker> = self.workers.iter().zip(chunks).map(|(worker, chunk)| {
+      let jobs = worker.jobs.as_ref().expect("evaluation thread stopped");
+      jobs.send(chunk.iter().map(|solution| solution.to_vec()).collect()).expect("evaluation thread failed");
+      worker
+    }).collect();
+
+    let local = &mut self.local;
+    let mut evaluations: Vec<Evaluation> = local_chunk.iter().map(|solution| local.evaluate(solution)).collect();
+    for worker in busy {
+      evaluations.extend(worker.results.recv().expect("evaluation thread failed"));
+    }
+    evaluations
+  }
+}
+
+impl Drop for EvaluatorPool {
+  fn drop(&mut self) {
+    for worker in self.workers.iter_mut() {
+      worker.jobs.take();
+      if let Some(thread) = worker.thread.take() {
+        thread.join().ok();
+      }
+    }
+  }
+}
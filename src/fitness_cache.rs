@@ -0,0 +1,131 @@
+use std::collections::{BTreeMap, HashMap};
+
+// The key of a solution in the cache. It's also the "Optimal ID" printed in the log.
+pub fn hash(solution: &[i64]) -> md5::Digest {
+  md5::compute(format!("{:?}", solution))
+}
+
+// Scores of the solutions already evaluated, so elites and repeated offspring are not decoded again.
+// When it's full, the least recently used score is dropped. A capacity of 0 disables it.
+pub struct FitnessCache<V: Copy> {
+  capacity: usize,
+  // Each score with the tick when it was last used.
+  entries: HashMap<md5::Digest, (V, u64)>,
+  // Keys by the tick when they were last used, oldest first.
+  recency: BTreeMap<u64, md5::Digest>,
+  tick: u64,
+  pub hits: i64,
+  pub lookups: i64
+}
+
+impl<V: Copy> FitnessCache<V> {
+  pub fn new(capacity: usize) -> FitnessCache<V> {
+    FitnessCache {
+      capacity,
+      entries: HashMap::new(),
+      recency: BTreeMap::new(),
+      tick: 0,
+      hits: 0,
+      lookups: 0
+    }
+  }
+
+  pub fn enabled(&self) -> bool {
+    self.capacity > 0
+  }
+
+  pub fn get(&mut self, key: &md5::Digest) -> Option<V> {
+    if !self.enabled() { return None; }
+
+    self.lookups += 1;
+    let tick = self.next_tick();
+    let entry = self.entries.get_mut(key)?;
+    self.recency.remove(&entry.1);
+    self.recency.insert(tick, *key);
+    entry.1 = tick;
+    self.hits += 1;
+    Some(entry.0)
+  }
+
+  pub fn insert(&mut self, key: md5::Digest, value: V) {
+    if !self.enabled() { return; }
+
+    let tick = self.next_tick();
+    if let Some((_, old_tick)) = self.entries.insert(key, (value, tick)) {
+      self.recency.remove(&old_tick);
+    }
+    self.recency.insert(tick, key);
+
+    while self.entries.len() > self.capacity {
+      let oldest = match self.recency.keys().next() {
+        Some(&oldest) => oldest,
+        None => break
+      };
+      if let Some(oldest_key) = self.recency.remove(&oldest) {
+        self.entries.remove(&oldest_key);
+      }
+    }
+  }
+
+  fn next_tick(&mut self) -> u64 {
+    self.tick += 1;
+    self.tick
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn key(n: i64) -> md5::Digest {
+    hash(&[n])
+  }
+
+  #[test]
+  fn evicts_the_least_recently_used() {
+    let mut cache = FitnessCache::<i64>::new(2);
+    cache.insert(key(1), 10);
+    cache.insert(key(2), 20);
+    cache.insert(key(3), 30);
+
+    assert_eq!(cache.get(&key(1)), None);
+    assert_eq!(cache.get(&key(2)), Some(20));
+    assert_eq!(cache.get(&key(3)), Some(30));
+  }
+
+  #[test]
+  fn hit_refreshes_recency() {
+    let mut cache = FitnessCache::<i64>::new(2);
+    cache.insert(key(1), 10);
+    cache.insert(key(2), 20);
+    assert_eq!(cache.get(&key(1)), Some(10));
+    cache.insert(key(3), 30);
+
+    assert_eq!(cache.get(&key(2)), None);
+    assert_eq!(cache.get(&key(1)), Some(10));
+    assert_eq!(cache.get(&key(3)), Some(30));
+    assert_eq!((cache.hits, cache.lookups), (3, 4));
+  }
+
+  #[test]
+  fn insert_updates_an_existing_key() {
+    let mut cache = FitnessCache::<i64>::new(2);
+    cache.insert(key(1), 10);
+    cache.insert(key(2), 20);
+    cache.insert(key(1), 11);
+    cache.insert(key(3), 30);
+
+    assert_eq!(cache.get(&key(1)), Some(11));
+    assert_eq!(cache.get(&key(2)), None);
+  }
+
+  #[test]
+  fn zero_capacity_disables_the_cache() {
+    let mut cache = FitnessCache::<i64>::new(0);
+    assert!(!cache.enabled());
+    cache.insert(key(1), 10);
+
+    assert_eq!(cache.get(&key(1)), None);
+    assert_eq!((cache.hits, cache.lookups), (0, 0));
+  }
+}
@@ -0,0 +1,30 @@
+// A gene packs the item index and its orientation into a single i64,
+// so solutions can stay as plain Vec<i64> permutations.
+// The lowest three bits are the orientation (see Shape::oriented).
+
+static ORIENTATION_BITS: i64 = 3;
+
+pub fn new(item_idx: usize, orientation: i64) -> i64 {
+  ((item_idx as i64) << ORIENTATION_BITS) | orientation
+}
+
+pub fn item_index(gene: i64) -> usize {
+  (gene >> ORIENTATION_BITS) as usize
+}
+
+pub fn orientation(gene: i64) -> i64 {
+  gene & ((1 << ORIENTATION_BITS) - 1)
+}
+
+pub fn with_orientation(gene: i64, orientation: i64) -> i64 {
+  new(item_index(gene), orientation)
+}
+
+// E.g. "[4R2M, 0, 3R]", with the orientation described by the item type.
+pub fn describe(solution: &[i64], describe_orientation: fn(i64) -> String) -> String {
+  let genes: Vec<String> = solution.iter().map(|&gene| {
+    format!("{}{}", item_index(gene), describe_orientation(orientation(gene)))
+  }).collect();
+
+  format!("[{}]", genes.join(", "))
+}
@@ -1,148 +1,211 @@
-use super::types::item::Item;
-use super::types::container::Container;
 use super::types::stats::Stats;
-use super::util;
 use super::math;
+use super::selection::{self, Selection};
+use super::crossover::{self, Crossover};
+use super::mutation::{self, Mutation};
+use super::scaling::{self, Scaling};
+use super::pareto;
+use super::objective::{self, Objective};
+use super::island::Topology;
+use super::fitness_cache::{self, FitnessCache};
+use super::evaluator_pool::EvaluatorPool;
+use super::gene;
 use rand::Rng;
 use rand::seq::SliceRandom;
-use md5;
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 // TODO: Creation of offspring can be improved.
 // TODO: Some functions should be put inside the GeneticAlgorithm struct.
 // TODO: Some stuff is stored in "Stats" and some other stuff in "GeneticAlgorithm". Make it consistent.
 // TODO: Change some types to smaller types (e.g. i64 -> i16).
-// TODO: Implement something like https://jp.mathworks.com/help/gads/genetic-algorithm-options.html#f9147
-//       For scaling (and possibly improving) fitness values.
 
-fn crossover(list1: &Vec<i64>, list2: &Vec<i64>, cross_probability: f32) -> Vec<i64> {
-  if list1.len() != list2.len() {
-    panic!("Lists must have the same length");
+// What the genetic algorithm needs to know about an item. Both flat (2D) items and boxes
+// implement it, so they are evolved by the same machinery.
+pub trait Evolvable: Clone + Send {
+  // Number of identical copies. Each copy is one gene.
+  fn quantity(&self) -> i64;
+  fn benefit(&self) -> i64;
+  // Every copy must be placed for a layout to be feasible.
+  fn required(&self) -> bool;
+  // Copies whose position is evolved, one gene each. Copies that are already placed have none.
+  fn evolved_quantity(&self) -> i64 {
+    self.quantity()
   }
+  // True if the item can be placed in more than one orientation.
+  fn reorientable(&self) -> bool;
+  fn random_orientation(&self) -> i64;
+  // Short description of an orientation for the final report, e.g. "R2M".
+  fn describe_orientation(orientation: i64) -> String;
+  // Explains the letters used by describe_orientation.
+  fn orientation_legend() -> &'static str;
+}
 
-  let mut list: i64 = if math::probability(0.5) { 0 } else { 1 };
-  let mut i = 0;
-  let mut j = 0;
-  let mut result: Vec<i64> = Vec::<i64>::new();
+// Summary of a decoded solution.
+pub struct Evaluation {
+  pub benefit: i64,
+  pub wasted: i64,
+  // Area (or volume) covered by the placed items.
+  pub used: i64,
+  pub container_benefits: Vec<i64>,
+  pub container_weights: Vec<i64>,
+  // Copies of required items that could not be placed.
+  pub missing_required: i64
+}
 
-  let mut added_values: HashSet<i64> = HashSet::<i64>::new();
+// Decodes solutions into layouts and evaluates them. Implementations keep their own scratch room.
+pub trait Evaluator: Send {
+  fn evaluate(&mut self, solution: &[i64]) -> Evaluation;
+}
 
-  // list 1 and 2 should be the same size and contain the same elements.
+// Parameters of the genetic algorithm. The defaults work well for the random scenarios,
+// but every instance is different, so each one can be set from the command line.
+#[derive(Clone)]
+pub struct GaConfig {
+  pub population_size: usize,
+  // What solutions are ranked by.
+  pub objective: Arc<dyn Objective>,
+  // Best solutions copied unchanged (before mutation) into the next generation.
+  pub elite_count: usize,
+  // Turns the scores into the fitness values used by the selection.
+  pub scaling: Arc<dyn Scaling>,
+  // Picks the solutions that breed the next generation.
+  pub selection: Arc<dyn Selection>,
+  // Builds each offspring from two parents.
+  pub crossover: Arc<dyn Crossover>,
+  // Each mutated solution gets one of these, picked at random.
+  pub mutations: Vec<Arc<dyn Mutation>>,
+  // Chance of leaving a solution unmutated.
+  pub mutation_skip_probability: f32,
+  // Chance of leaving each gene unchanged when a solution is mutated.
+  pub gene_skip_probability: f32,
+  // False to copy the elite solutions unmutated into the next generation.
+  pub mutate_elites: bool,
+  // Raises the chance of mutating a solution while the optimal score doesn't improve.
+  pub adaptive_mutation: bool,
+  // Multi-objective mode: maximizes the benefit and minimizes the wasted room (NSGA-II),
+  // keeping the Pareto front. The scaling and selection are not used.
+  pub pareto: bool,
+  // Island model: number of populations evolved on separate threads (see island::run).
+  pub islands: usize,
+  // Generations between migrations.
+  pub migration_interval: i64,
+  // Best solutions each island sends on every migration.
+  pub migrants: usize,
+  pub migration_topology: Topology,
+  // Threads evaluating each generation (per island).
+  pub threads: usize,
+  // Makes the run repeatable. Several islands still exchange migrants in whatever order their threads run.
+  pub seed: Option<u64>,
+  // Scores kept in each island's fitness cache. 0 disables it.
+  pub cache_size: usize
+}
 
-  loop {
-    if (i == list1.len()) && (j == list2.len()) {
-      break;
+// With adaptive mutation, the chance of mutating a solution grows by its initial value (but at least
+// by ADAPTIVE_MUTATION_MIN_STEP) every this many generations without a new optimal, until solutions are always mutated.
+static ADAPTIVE_MUTATION_GENERATIONS: i64 = 20;
+// So the chance still grows when mutation is turned off (or almost) to begin with.
+static ADAPTIVE_MUTATION_MIN_STEP: f32 = 0.1;
+
+impl Default for GaConfig {
+  fn default() -> GaConfig {
+    GaConfig {
+      population_size: 100,
+      objective: Arc::new(objective::BenefitThenWaste),
+      elite_count: 10,
+      scaling: Arc::new(scaling::Identity),
+      selection: Arc::new(selection::Legacy { min_survivors: 10 }),
+      crossover: Arc::new(crossover::Legacy { switch_probability: 0.1 }),
+      mutations: vec![Arc::new(mutation::Legacy)],
+      mutation_skip_probability: 0.9,
+      gene_skip_probability: 0.8,
+      mutate_elites: true,
+      adaptive_mutation: false,
+      pareto: false,
+      islands: 1,
+      migration_interval: 10,
+      migrants: 2,
+      migration_topology: Topology::Ring,
+      threads: 1,
+      seed: None,
+      cache_size: 10000
     }
+  }
+}
 
-    // This dummy value can be avoided by ensuring the current list always has items left.
-    let mut val: i64 = -1;
+// Score lost for each missing copy of a required item. It's greater than the
+// benefit of placing every item, so a feasible layout always beats an infeasible one.
+pub fn required_penalty<I: Evolvable>(items: &[I]) -> i64 {
+  items.iter().map(|item| item.benefit() * item.quantity()).sum::<i64>() + 1
+}
 
-    if list == 0 && i < list1.len() {
-      val = list1[i];
-      i += 1;
-    } else if j < list2.len() {
-      val = list2[j];
-      j += 1;
-    }
+// Mutates the solution with the given chance, using one of the configured mutations picked at random.
+fn mutate<I: Evolvable>(list: &mut [i64], items: &[I], config: &GaConfig, mutation_probability: f32) {
+  if !math::probability(mutation_probability) { return; }
 
-    if val != -1 && !added_values.contains(&val) {
-      result.push(val);
-      added_values.insert(val);
-    }
+  let reorient = |item_gene: i64| {
+    let item = &items[gene::item_index(item_gene)];
+    if item.reorientable() { Some(gene::with_orientation(item_gene, item.random_orientation())) } else { None }
+  };
 
-    // Use different list?
-    if math::probability(cross_probability) {
-      list = if list == 0 { 1 } else { 0 };
-    }
+  if let Some(mutation) = config.mutations.choose(&mut math::rng()) {
+    mutation.mutate(list, 1.0 - config.gene_skip_probability, &reorient);
   }
+}
 
-  result
+fn make_offspring<I: Evolvable>(list1: &[i64], list2: &[i64], items: &[I], config: &GaConfig, mutation_probability: f32) -> Vec<i64> {
+  let mut cross = config.crossover.cross(list1, list2);
+  mutate(&mut cross, items, config, mutation_probability);
+  cross
 }
 
-fn mutate(list: &mut Vec<i64>) {
-  if math::probability(0.9) { return; }
-  for i in 0..list.len() {
-    if math::probability(0.8) { continue; }
-    let range = (i + 1) as i64 .. list.len() as i64;
-    if range.is_empty() { continue; }
-    let idx: i64 = rand::thread_rng().gen_range(range);
-    let temp = list[idx as usize];
-    list[idx as usize] = list[i as usize];
-    list[i] = temp;
+// Items with a quantity greater than one appear once per copy. Items that are already placed don't appear.
+fn random_solution<I: Evolvable>(items: &[I]) -> Vec<i64> {
+  let mut sol: Vec<i64> = Vec::<i64>::new();
+  for (idx, item) in items.iter().enumerate() {
+    for _ in 0..item.evolved_quantity() {
+      sol.push(gene::new(idx, item.random_orientation()));
+    }
   }
+  sol.shuffle(&mut math::rng());
+  sol
 }
 
-fn make_offspring(list1: &Vec<i64>, list2: &Vec<i64>) -> Vec<i64> {
-  let mut cross = crossover(list1, list2, 0.1);
-  mutate(&mut cross);
-  cross
+// How a solution ranks: the value of the objective, and the benefit and wasted room it comes from.
+#[derive(Clone, Copy)]
+struct Score {
+  benefit: i64,
+  wasted: i64,
+  value: f64
 }
 
-fn random_solution(n: i64) -> Vec<i64> {
-  let mut sol: Vec<i64> = (0..n).collect();
-  sol.shuffle(&mut rand::thread_rng());
-  sol
+// Layouts missing required items get a negative benefit.
+fn score(evaluation: &Evaluation, required_penalty: i64, objective: &dyn Objective) -> Score {
+  let benefit = evaluation.benefit - (evaluation.missing_required * required_penalty);
+  Score { benefit, wasted: evaluation.wasted, value: objective.value(benefit, evaluation.wasted, evaluation.used) }
 }
 
-fn count_zeros(filled: &Vec<Vec<i64>>) -> i64 {
-  let mut total = 0;
-  for i in 0..filled.len() {
-    for j in 0..filled[i].len() {
-      if filled[i][j] == 0 {
-        total += 1;
-      }
-    }
-  }
-  total
-}
-
-fn score(container: &Container, items: &[Item], solution: &Vec<i64>) -> (i64, i64) {
-  // TODO: A static matrix would be faster (reset with memset or something similar).
-  let mut filled = vec![vec![0; container.width as usize]; container.height as usize];
-
-  let mut total_benefit = 0;
-
-  // Place every item until there's no room left.
-  for i in 0..solution.len() {
-    let item_idx = solution[i] as usize;
-    let item = &items[item_idx];
-    let tuple = util::first_empty_space(&filled, item);
-    match tuple {
-      Some(values) => {
-        total_benefit += item.benefit;
-        let (row, col) = values;
-        // Mark all cells as used.
-        // TODO: Something faster like fill or memset would be better.
-        for i in row..(row + item.height) {
-          for j in col..(col + item.width) {
-            filled[i as usize][j as usize] = 1;
-          }
-        }
-      },
-      None => {
-        // If we add this break, then the allocating heuristic changes.
-        // What's important is to keep the heuristic the same during the program execution
-        // (i.e. don't use different heuristics for different things).
-        // break;
-      }
-    }
-  }
+// Higher is better: the value first, then the least wasted room if the objective cares about it.
+fn rank_key(objective: &dyn Objective, value: f64, wasted: i64) -> (f64, i64) {
+  (value, if objective.breaks_ties_by_waste() { -wasted } else { 0 })
+}
 
-  let wasted_space = count_zeros(&filled);
-  (total_benefit, wasted_space)
+// For sorting from best to worst.
+fn compare_scores(objective: &dyn Objective, a: &Score, b: &Score) -> std::cmp::Ordering {
+  let (key_a, key_b) = (rank_key(objective, a.value, a.wasted), rank_key(objective, b.value, b.wasted));
+  key_b.partial_cmp(&key_a).unwrap_or(std::cmp::Ordering::Equal)
 }
 
-fn generate_new_population(survived_solutions: &mut Vec<&Vec<i64>>) -> Vec<Vec<i64>>{
+// The ranked solutions are sorted from best to worst. The survived solutions are the mating pool picked by the selection.
+fn generate_new_population<I: Evolvable>(ranked_solutions: &[&Vec<i64>], survived_solutions: &[&Vec<i64>], items: &[I], config: &GaConfig, mutation_probability: f32) -> Vec<Vec<i64>>{
   let mut result = Vec::<Vec<i64>>::new();
 
   // Add elite first.
-  for i in 0..survived_solutions.len(){
-    if i >= survived_solutions.len() { break; }
-    if i > 10 { break; }
-    result.push(survived_solutions[i].to_vec());
+  for solution in ranked_solutions.iter().take(config.elite_count) {
+    result.push(solution.to_vec());
   }
 
-  while result.len() < 100 {
+  while result.len() < config.population_size {
     for i in 0..survived_solutions.len() {
       if math::probability(0.01) {
         result.push(survived_solutions[i].to_vec());
@@ -151,101 +214,336 @@ fn generate_new_population(survived_solutions: &mut Vec<&Vec<i64>>) -> Vec<Vec<i
       if math::probability(0.2) {
         let range = (i + 1) as i64 .. survived_solutions.len() as i64;
         if range.is_empty() { continue; }
-        let crossover_idx: i64 = rand::thread_rng().gen_range(range);
+        let crossover_idx: i64 = math::rng().gen_range(range);
         let crossover_solution = survived_solutions[crossover_idx as usize];
-        let offspring = make_offspring(survived_solutions[i], crossover_solution);
+        let offspring = make_offspring(survived_solutions[i], crossover_solution, items, config, mutation_probability);
         result.push(offspring);
       }
     }
   }
 
-  // TODO: Maybe try avoid mutating elite (i.e. first few items). And also append mutated elite.
-  for sol in &mut result {
-    mutate(sol);
+  // Elites come first, so they can be left out.
+  let unmutated = if config.mutate_elites { 0 } else { std::cmp::min(config.elite_count, ranked_solutions.len()) };
+  for sol in result.iter_mut().skip(unmutated) {
+    mutate(sol, items, config, mutation_probability);
   }
 
   result
 }
 
-pub struct GeneticAlgorithm {
-  items: Vec<Item>,
+// Stores the solution in the stats if it beats the current optimal.
+// Returns true when it's the global optimum.
+fn update_optimal(stats: &mut Stats, evaluator: &mut dyn Evaluator, objective: &dyn Objective, solution: &[i64], score: Score) -> bool {
+  // New optimal found.
+  if rank_key(objective, score.value, score.wasted) > rank_key(objective, stats.optimal_value, stats.optimal_wasted) {
+    stats.optimal_best_score = score.benefit;
+    stats.optimal_value = score.value;
+    stats.optimal_hash = format!("{:?}", fitness_cache::hash(solution));
+    stats.optimal_wasted = score.wasted;
+    stats.optimal_found_gens.push(stats.total_generations);
+    stats.store_optimal_solution(solution);
+    let evaluation = evaluator.evaluate(solution);
+    stats.optimal_container_benefits = evaluation.container_benefits;
+    stats.optimal_container_weights = evaluation.container_weights;
+    stats.optimal_missing_required = evaluation.missing_required;
+
+    // For now, this program doesn't try to minimize wasted room.
+    // So even if it's 0, that doesn't mean it's the optimal value.
+    // if stats.optimal_wasted == 0 {
+    //   return true;
+    // }
+
+    if objective.stops_at_max_benefit() && stats.is_feasible() && stats.optimal_best_score == stats.max_possible_score {
+      return true;
+    }
+  }
+
+  false
+}
+
+// A solution with its score.
+type ScoredSolution = (Vec<i64>, Score);
+
+pub struct GeneticAlgorithm<I: Evolvable> {
+  items: Vec<I>,
   solutions: Vec::<Vec<i64>>,
-  container: Container
+  // One evaluator per evaluation thread.
+  evaluators: EvaluatorPool,
+  required_penalty: i64,
+  config: GaConfig,
+  // Multi-objective mode: the solutions kept from the previous generations, with their scores.
+  parents: Vec<ScoredSolution>,
+  // Best solutions of the last generation, sent to other islands.
+  best_solutions: Vec<Vec<i64>>,
+  // Tells the islands apart in the log.
+  log_prefix: String,
+  // Scores of solutions from previous generations.
+  cache: FitnessCache<Score>
 }
 
-impl GeneticAlgorithm {
-  pub fn new(container: Container, items: &Vec<Item>) -> GeneticAlgorithm {
+impl<I: Evolvable> GeneticAlgorithm<I> {
+  pub fn new(items: &[I], evaluators: Vec<Box<dyn Evaluator>>, config: GaConfig) -> GeneticAlgorithm<I> {
     let mut solutions = Vec::<Vec<i64>>::new();
 
-    for _ in 0..100 {
-      solutions.push(random_solution(items.len() as i64));
+    for _ in 0..config.population_size {
+      solutions.push(random_solution(items));
     }
 
+    let cache = FitnessCache::new(config.cache_size);
+
     GeneticAlgorithm {
       items: items.to_vec(),
-      solutions: solutions,
-      container: container
+      solutions,
+      evaluators: EvaluatorPool::new(evaluators),
+      required_penalty: required_penalty(items),
+      config,
+      parents: Vec::<ScoredSolution>::new(),
+      best_solutions: Vec::<Vec<i64>>::new(),
+      log_prefix: String::new(),
+      cache
+    }
+  }
+
+  pub fn with_island_index(mut self, island_idx: usize) -> GeneticAlgorithm<I> {
+    self.log_prefix = format!("Island #{} | ", island_idx + 1);
+    self
+  }
+
+  // Copies of the best solutions of the last generation.
+  pub fn emigrants(&self) -> Vec<Vec<i64>> {
+    self.best_solutions.clone()
+  }
+
+  // Migrants from another island replace the last solutions of the next generation (never the elites).
+  pub fn immigrate(&mut self, migrants: Vec<Vec<i64>>) {
+    let start = std::cmp::max(self.config.elite_count, self.solutions.len().saturating_sub(migrants.len()));
+    for (solution, migrant) in self.solutions.iter_mut().skip(start).zip(migrants) {
+      *solution = migrant;
+    }
+  }
+
+  // Keeps the island's optimal in the shared stats if it's better than the one there, and adds its Pareto front.
+  pub fn merge_stats(&self, shared: &mut Stats, island: &Stats) {
+    let objective = self.config.objective.as_ref();
+    shared.total_generations = std::cmp::max(shared.total_generations, island.total_generations);
+    if rank_key(objective, island.optimal_value, island.optimal_wasted) > rank_key(objective, shared.optimal_value, shared.optimal_wasted) {
+      shared.copy_optimal(island);
+      // The island has already counted the generation where it found its optimal.
+      shared.optimal_found_gens.push(island.optimal_found_gens.last().copied().unwrap_or(island.total_generations));
+    }
+    for point in &island.pareto_front {
+      pareto::update_front(&mut shared.pareto_front, point.benefit, point.wasted, &point.solution);
+    }
+  }
+
+  // Adds the fitness cache hits of this population to the stats.
+  pub fn add_cache_stats(&self, stats: &mut Stats) {
+    stats.cache_hits += self.cache.hits;
+    stats.cache_lookups += self.cache.lookups;
+  }
+
+  // Scores of the current solutions, in the same order. Solutions in the cache are not evaluated again,
+  // and solutions repeated in the generation are only evaluated once.
+  fn score_solutions(&mut self) -> Vec<Score> {
+    let keys: Vec<md5::Digest> = self.solutions.iter().map(|solution| fitness_cache::hash(solution)).collect();
+    let cached: Vec<Option<Score>> = keys.iter().map(|key| self.cache.get(key)).collect();
+
+    // Position of each solution to evaluate in `missing`.
+    let mut missing_positions = HashMap::<md5::Digest, usize>::new();
+    let mut missing = Vec::<&[i64]>::new();
+    let mut missing_keys = Vec::<md5::Digest>::new();
+    for (idx, key) in keys.iter().enumerate() {
+      if cached[idx].is_none() && !missing_positions.contains_key(key) {
+        missing_positions.insert(*key, missing.len());
+        missing.push(&self.solutions[idx]);
+        missing_keys.push(*key);
+      }
+    }
+
+    let objective = self.config.objective.as_ref();
+    let evaluations = self.evaluators.evaluate_all(&missing);
+    let scores: Vec<Score> = evaluations.iter().map(|evaluation| score(evaluation, self.required_penalty, objective)).collect();
+    for (key, &score) in missing_keys.into_iter().zip(scores.iter()) {
+      self.cache.insert(key, score);
+    }
+
+    keys.iter().zip(cached).map(|(key, cached)| cached.unwrap_or_else(|| scores[missing_positions[key]])).collect()
+  }
+
+  // Chance of mutating each solution of the next generation.
+  fn mutation_probability(&self, stats: &Stats) -> f32 {
+    let probability = 1.0 - self.config.mutation_skip_probability;
+    if !self.config.adaptive_mutation {
+      return probability;
     }
+
+    let stagnant_generations = stats.total_generations - stats.optimal_found_gens.last().copied().unwrap_or(0);
+    let steps = (stagnant_generations / ADAPTIVE_MUTATION_GENERATIONS) as f32;
+    f32::min(1.0, probability + steps * f32::max(probability, ADAPTIVE_MUTATION_MIN_STEP))
+  }
+
+  // Multi-objective mode (NSGA-II): the new solutions compete with the parents kept from the previous
+  // generation, and the best ones by crowded comparison (see pareto::crowded_order) are kept as the next parents.
+  fn execute_pareto_population(&mut self, stats: &mut Stats) -> bool {
+    let mut population = std::mem::take(&mut self.parents);
+    let scores = self.score_solutions();
+    population.extend(std::mem::take(&mut self.solutions).into_iter().zip(scores));
+
+    let points: Vec<(i64, i64)> = population.iter().map(|member| (member.1.benefit, member.1.wasted)).collect();
+    let mut order = pareto::crowded_order(&points);
+
+    // Only feasible layouts go into the front that's reported.
+    for (solution, score) in population.iter().filter(|member| member.1.benefit >= 0) {
+      pareto::update_front(&mut stats.pareto_front, score.benefit, score.wasted, solution);
+    }
+
+    // The optimal reported is still the best by the objective.
+    let objective = self.config.objective.as_ref();
+    let best = population.iter().min_by(|a, b| compare_scores(objective, &a.1, &b.1)).unwrap();
+    if update_optimal(stats, self.evaluators.local(), objective, &best.0, best.1) {
+      return true;
+    }
+
+    order.truncate(self.config.population_size);
+    let mut population: Vec<Option<ScoredSolution>> = population.into_iter().map(Some).collect();
+    self.parents = order.iter().filter_map(|&idx| population[idx].take()).collect();
+    self.best_solutions = self.parents.iter().take(self.config.migrants).map(|parent| parent.0.clone()).collect();
+
+    // The parents are sorted by crowded comparison, so a binary tournament on their
+    // position is the NSGA-II crowded tournament. Elites are not needed, the parents are kept anyway.
+    let ranked_solutions: Vec<&Vec<i64>> = self.parents.iter().map(|parent| &parent.0).collect();
+    let positions: Vec<f64> = (0..ranked_solutions.len()).map(|idx| -(idx as f64)).collect();
+    let survived_solutions: Vec<&Vec<i64>> = selection::Tournament { size: 2 }.select(&positions, self.config.population_size)
+                                                                               .into_iter()
+                                                                               .map(|idx| ranked_solutions[idx])
+                                                                               .collect();
+
+    let mutation_probability = self.mutation_probability(stats);
+    self.solutions = generate_new_population(&[], &survived_solutions, &self.items, &self.config, mutation_probability);
+
+    // Distinct points of the first front among the parents (several solutions may share the same point).
+    let mut first_front: Vec<(i64, i64)> = self.parents.iter()
+                                                       .map(|parent| (parent.1.benefit, parent.1.wasted))
+                                                       .take_while(|&point| !self.parents.iter().any(|other| pareto::dominates((other.1.benefit, other.1.wasted), point)))
+                                                       .collect();
+    first_front.sort_unstable();
+    first_front.dedup();
+    println!("{}Gen #{} | Front points: {} | Feasible front points found: {} | Current optimal: {} | Optimal ID: {} | Wasted room: {}", self.log_prefix, stats.total_generations, first_front.len(), stats.pareto_front.len(), stats.optimal_best_score, stats.optimal_hash, stats.optimal_wasted);
+
+    stats.total_generations += 1;
+
+    false
   }
 
   pub fn execute_population(&mut self, stats: &mut Stats) -> bool{ 
-    let mut all_scores = Vec::<i64>::new();
+    if self.config.pareto {
+      return self.execute_pareto_population(stats);
+    }
 
-    // Solutions that survived.
-    let mut survived_solutions = Vec::<&Vec<i64>>::new();
-    let mut survived_scores = Vec::<i64>::new();
+    let mut all_scores = Vec::<f64>::new();
 
-    // List of solution and score (which is also a tuple).
-    let mut tuples = Vec::<(&Vec<i64>, (i64, i64))>::new();
+    // List of solution and score.
+    let mut tuples = Vec::<(&Vec<i64>, Score)>::new();
 
     // Eval many solutions.
-    for solution in &self.solutions {
-      let score = score(&self.container, &self.items, &solution);
-      all_scores.push(score.0);
-      tuples.push((&solution, score));
-    }
-
-    tuples.sort_by_key(|k| k.1);
-    tuples.reverse();
-    let gen_best_score: i64 = tuples[0].1.0;
-
-    // New optimal found.
-    if gen_best_score > stats.optimal_best_score {
-      stats.optimal_best_score = gen_best_score;
-      stats.optimal_hash = format!("{:?}", md5::compute(format!("{:?}", tuples[0].0)));
-      stats.optimal_wasted = tuples[0].1.1;
-      stats.optimal_found_gens.push(stats.total_generations);
-      stats.store_optimal_solution(tuples[0].0);
-
-      // For now, this program doesn't try to minimize wasted room.
-      // So even if it's 0, that doesn't mean it's the optimal value.
-      // if stats.optimal_wasted == 0 {
-      //   return true;
-      // }
-
-      if stats.optimal_best_score == stats.max_possible_score {
-        return true;
-      }
+    let scores = self.score_solutions();
+    let objective = self.config.objective.as_ref();
+    for (solution, score) in self.solutions.iter().zip(scores) {
+      all_scores.push(score.benefit as f64);
+      tuples.push((solution, score));
     }
 
-    let stddev: f64 = math::standard_deviation(&all_scores);
+    // Best first. Solutions with the same value keep their order, unless the objective breaks ties by the least wasted room.
+    tuples.sort_by(|a, b| compare_scores(objective, &a.1, &b.1));
+    let gen_best_score: i64 = tuples[0].1.benefit;
 
-    for tuple in &tuples {
-      // Ensure a minimum number of solutions.
-      if survived_solutions.len() < 10 || tuple.1.0 > (stddev as i64) {
-        survived_solutions.push(tuple.0);
-        survived_scores.push(tuple.1.0);
-      } else {
-        break;
-      }
+    if update_optimal(stats, self.evaluators.local(), objective, tuples[0].0, tuples[0].1) {
+      return true;
     }
 
-    self.solutions = generate_new_population(&mut survived_solutions);
+    let ranked_solutions: Vec<&Vec<i64>> = tuples.iter().map(|tuple| tuple.0).collect();
+    self.best_solutions = ranked_solutions.iter().take(self.config.migrants).map(|solution| solution.to_vec()).collect();
+    let ranked_values: Vec<f64> = tuples.iter().map(|tuple| tuple.1.value).collect();
+    let fitness = self.config.scaling.scale(&ranked_values);
 
-    println!("Gen #{} | Best score: {} | Gen avg: {:.2} | Current optimal: {} | Optimal ID: {} | Wasted room: {}", stats.total_generations, gen_best_score, math::mean(&all_scores), stats.optimal_best_score, stats.optimal_hash, stats.optimal_wasted);
+    // Solutions that survived.
+    let survived_solutions: Vec<&Vec<i64>> = self.config.selection.select(&fitness, self.config.population_size)
+                                                                  .into_iter()
+                                                                  .map(|idx| ranked_solutions[idx])
+                                                                  .collect();
+
+    let mutation_probability = self.mutation_probability(stats);
+    self.solutions = generate_new_population(&ranked_solutions, &survived_solutions, &self.items, &self.config, mutation_probability);
+
+    let adaptive = if self.config.adaptive_mutation { format!(" | Mutation chance: {:.2}", mutation_probability) } else { String::new() };
+    println!("{}Gen #{} | Best score: {} | Gen avg: {:.2} | Fitness (best/avg): {:.3}/{:.3} | Current optimal: {} | Optimal ID: {} | Wasted room: {}{}", self.log_prefix, stats.total_generations, gen_best_score, math::mean(&all_scores), fitness[0], math::mean(&fitness), stats.optimal_best_score, stats.optimal_hash, stats.optimal_wasted, adaptive);
 
     stats.total_generations += 1;
 
-    return false;
+    false
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Clone)]
+  struct TestItem;
+
+  impl Evolvable for TestItem {
+    fn quantity(&self) -> i64 { 1 }
+    fn benefit(&self) -> i64 { 10 }
+    fn required(&self) -> bool { false }
+    fn reorientable(&self) -> bool { false }
+    fn random_orientation(&self) -> i64 { 0 }
+    fn describe_orientation(_orientation: i64) -> String { String::new() }
+    fn orientation_legend() -> &'static str { "" }
+  }
+
+  // One point for each pair of neighbour items in ascending order, so the benefit never reaches
+  // the maximum possible score and the run doesn't stop.
+  struct AscendingPairs;
+
+  impl Evaluator for AscendingPairs {
+    fn evaluate(&mut self, solution: &[i64]) -> Evaluation {
+      let benefit = solution.windows(2).filter(|pair| gene::item_index(pair[0]) < gene::item_index(pair[1])).count() as i64;
+      Evaluation { benefit, wasted: 0, used: 0, container_benefits: vec![benefit], container_weights: vec![0], missing_required: 0 }
+    }
+  }
+
+  #[test]
+  fn merged_stats_keep_the_generations_of_the_island() {
+    math::seed_rng(1);
+    let items = vec![TestItem; 12];
+    let mut genetic_algorithm = GeneticAlgorithm::new(&items, vec![Box::new(AscendingPairs)], GaConfig::default());
+    let mut island_stats = Stats::new(&items, vec![None]);
+    let mut shared_stats = island_stats.clone();
+
+    // Same steps as island::run with a single island.
+    for _ in 0..30 {
+      assert!(!genetic_algorithm.execute_population(&mut island_stats));
+      genetic_algorithm.merge_stats(&mut shared_stats, &island_stats);
+    }
+
+    assert!(!island_stats.optimal_found_gens.is_empty());
+    assert_eq!(shared_stats.optimal_found_gens, island_stats.optimal_found_gens);
+    assert_eq!(shared_stats.optimal_best_score, island_stats.optimal_best_score);
+  }
+
+  #[test]
+  fn adaptive_mutation_grows_from_zero() {
+    let items = vec![TestItem; 4];
+    let config = GaConfig { mutation_skip_probability: 1.0, adaptive_mutation: true, ..GaConfig::default() };
+    let genetic_algorithm = GeneticAlgorithm::new(&items, vec![Box::new(AscendingPairs)], config);
+    let mut stats = Stats::new(&items, vec![None]);
+    stats.optimal_found_gens.push(5);
+
+    stats.total_generations = 24;
+    assert_eq!(genetic_algorithm.mutation_probability(&stats), 0.0);
+    stats.total_generations = 45;
+    assert!((genetic_algorithm.mutation_probability(&stats) - 0.2).abs() < 1e-6);
+    stats.total_generations = 1000;
+    assert_eq!(genetic_algorithm.mutation_probability(&stats), 1.0);
   }
 }
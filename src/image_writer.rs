@@ -1,83 +1,213 @@
 extern crate image;
-use super::util;
+use super::decoder::{self, Decoder, Layout};
+use super::box_decoder::{LayerDecoder, BoxLayout};
+use super::types::box_item::BoxItem;
+use super::types::box_container::BoxContainer;
 use super::types::item::Item;
 use super::types::container::Container;
+use super::types::rect::Rect;
+use super::types::shape::Shape;
 use image::RgbImage;
 
-static TILE_SIZE: i64 = 10;
-
-fn draw_solution(img: &mut RgbImage, container: &Container, items: &[Item], solution: &Vec<i64>) {
-  let mut filled = vec![vec![0; container.width as usize]; container.height as usize];
-
-  // Place every item until there's no room left.
-  for i in 0..solution.len() {
-    let item_idx = solution[i] as usize;
-    let item = &items[item_idx];
-    let tuple = util::first_empty_space(&filled, item);
-    match tuple {
-      Some(values) => {
-        let (row, col) = values;
-
-        for i in row..(row + item.height) {
-          for j in col..(col + item.width) {
-            filled[i as usize][j as usize] = 1;
-          }
-        }
-
-        draw_background(img, col, row, item, (193, 101, 10));
-
-        // Draw four sides.
-        draw_horizontal_line(img, col, row, item.width, (255, 255, 255));
-        draw_horizontal_line(img, col, row + item.height, item.width, (255, 255, 255));
-        draw_vertical_line(img, col, row, item.height, (255, 255, 255));
-        draw_vertical_line(img, col + item.width, row, item.height, (255, 255, 255));
-      },
-      None => {}
+// Pixels per container cell. Large containers are scaled down so the image stays a reasonable size.
+static MAX_TILE_SIZE: f64 = 10.0;
+static MAX_IMAGE_SIDE: f64 = 2000.0;
+
+// Grid lines are only drawn when cells are big enough to tell them apart.
+static MIN_TILE_SIZE_FOR_GRID: f64 = 4.0;
+
+fn tile_size(width: i64, height: i64) -> f64 {
+  let longest_side = std::cmp::max(width, height) as f64;
+  f64::min(MAX_TILE_SIZE, MAX_IMAGE_SIDE / longest_side)
+}
+
+fn to_pixel(n: i64, tile: f64) -> u32 {
+  (n as f64 * tile).round() as u32
+}
+
+fn draw_solution(img: &mut RgbImage, tile: f64, items: &[Item], layout: &Layout, container_idx: usize) {
+  for placed in layout.placed_items.iter().filter(|placed| placed.container_idx == container_idx) {
+    let (row, col) = (placed.row, placed.col);
+    let item = &items[placed.item_idx];
+    let shape = item.shape(placed.orientation);
+
+    // Items that were already placed are drawn in blue.
+    let rgb = if item.fixed.is_some() { (70, 110, 160) } else { (193, 101, 10) };
+
+    if shape.is_rectangle() {
+      draw_rectangle(img, tile, col, row, shape.width, shape.height, rgb);
+    } else {
+      draw_shape(img, tile, col, row, shape, rgb);
     }
   }
 }
 
-fn draw_background(img: &mut RgbImage, x: i64, y: i64, item: &Item, rgb: (u8, u8, u8)) {
-  for w in 0..(item.width * TILE_SIZE) {
-    for h in 0..(item.height * TILE_SIZE) {
-      let pixel_x = (x * TILE_SIZE) + w;
-      let pixel_y = (y * TILE_SIZE) + h;
-      img.get_pixel_mut(pixel_x as u32, pixel_y as u32).data = [rgb.0, rgb.1, rgb.2];
+// Draws the gap kept around items with spacing. Items are drawn on top of it afterwards.
+fn draw_spacing(img: &mut RgbImage, tile: f64, container: &Container, items: &[Item], layout: &Layout, container_idx: usize) {
+  for placed in layout.placed_items.iter().filter(|placed| placed.container_idx == container_idx) {
+    let item = &items[placed.item_idx];
+    if item.spacing == 0 { continue; }
+
+    for rect in &item.shape(placed.orientation).rects {
+      let rect = Rect::new(placed.row + rect.row, placed.col + rect.col, rect.width, rect.height);
+      if let Some(margin) = decoder::spacing_margin(&rect, item.spacing, container.width, container.height) {
+        draw_background(img, tile, margin.col, margin.row, margin.width, margin.height, (245, 215, 180));
+      }
     }
   }
 }
 
-fn draw_horizontal_line(img: &mut RgbImage, x: i64, y: i64, length: i64, rgb: (u8, u8, u8)) {
-  for i in 0..(length * TILE_SIZE) + 1 {
-    let pixel_x = (x * TILE_SIZE) + i;
-    let pixel_y = y * TILE_SIZE;
-    img.get_pixel_mut(pixel_x as u32, pixel_y as u32).data = [rgb.0, rgb.1, rgb.2];
+fn draw_rectangle(img: &mut RgbImage, tile: f64, x: i64, y: i64, width: i64, height: i64, rgb: (u8, u8, u8)) {
+  draw_background(img, tile, x, y, width, height, rgb);
+
+  // Draw four sides.
+  draw_horizontal_line(img, tile, x, y, width, (255, 255, 255));
+  draw_horizontal_line(img, tile, x, y + height, width, (255, 255, 255));
+  draw_vertical_line(img, tile, x, y, height, (255, 255, 255));
+  draw_vertical_line(img, tile, x + width, y, height, (255, 255, 255));
+}
+
+// Non-rectangular shapes are drawn cell by cell, only outlining the sides that face outside the shape.
+fn draw_shape(img: &mut RgbImage, tile: f64, x: i64, y: i64, shape: &Shape, rgb: (u8, u8, u8)) {
+  for row in 0..shape.height {
+    for col in 0..shape.width {
+      if !shape.contains_cell(row, col) { continue; }
+      draw_background(img, tile, x + col, y + row, 1, 1, rgb);
+    }
+  }
+
+  for row in 0..shape.height {
+    for col in 0..shape.width {
+      if !shape.contains_cell(row, col) { continue; }
+      let (cell_x, cell_y) = (x + col, y + row);
+      if !shape.contains_cell(row - 1, col) { draw_horizontal_line(img, tile, cell_x, cell_y, 1, (255, 255, 255)); }
+      if !shape.contains_cell(row + 1, col) { draw_horizontal_line(img, tile, cell_x, cell_y + 1, 1, (255, 255, 255)); }
+      if !shape.contains_cell(row, col - 1) { draw_vertical_line(img, tile, cell_x, cell_y, 1, (255, 255, 255)); }
+      if !shape.contains_cell(row, col + 1) { draw_vertical_line(img, tile, cell_x + 1, cell_y, 1, (255, 255, 255)); }
+    }
   }
 }
 
-fn draw_vertical_line(img: &mut RgbImage, x: i64, y: i64, length: i64, rgb: (u8, u8, u8)) {
-  for i in 0..(length * TILE_SIZE) + 1 {
-    let pixel_x = x * TILE_SIZE;
-    let pixel_y = (y * TILE_SIZE) + i;
-    img.get_pixel_mut(pixel_x as u32, pixel_y as u32).data = [rgb.0, rgb.1, rgb.2];
+fn draw_background(img: &mut RgbImage, tile: f64, x: i64, y: i64, width: i64, height: i64, rgb: (u8, u8, u8)) {
+  for pixel_x in to_pixel(x, tile)..to_pixel(x + width, tile) {
+    for pixel_y in to_pixel(y, tile)..to_pixel(y + height, tile) {
+      img.get_pixel_mut(pixel_x, pixel_y).data = [rgb.0, rgb.1, rgb.2];
+    }
   }
 }
 
-pub fn create_image(file_name: String, container: &Container, items: &Vec<Item>, solution: &Vec<i64>) {
+fn draw_horizontal_line(img: &mut RgbImage, tile: f64, x: i64, y: i64, length: i64, rgb: (u8, u8, u8)) {
+  let pixel_y = to_pixel(y, tile);
+  for pixel_x in to_pixel(x, tile)..to_pixel(x + length, tile) + 1 {
+    img.get_pixel_mut(pixel_x, pixel_y).data = [rgb.0, rgb.1, rgb.2];
+  }
+}
+
+fn draw_vertical_line(img: &mut RgbImage, tile: f64, x: i64, y: i64, length: i64, rgb: (u8, u8, u8)) {
+  let pixel_x = to_pixel(x, tile);
+  for pixel_y in to_pixel(y, tile)..to_pixel(y + length, tile) + 1 {
+    img.get_pixel_mut(pixel_x, pixel_y).data = [rgb.0, rgb.1, rgb.2];
+  }
+}
+
+fn draw_border(img: &mut RgbImage, tile: f64, x: i64, y: i64, width: i64, height: i64) {
+  if tile >= MIN_TILE_SIZE_FOR_GRID {
+    for i in 0..width + 1 {
+      draw_vertical_line(img, tile, x + i, y, height, (50, 50, 50));
+    }
+
+    for i in 0..height + 1 {
+      draw_horizontal_line(img, tile, x, y + i, width, (50, 50, 50));
+    }
+  } else {
+    draw_vertical_line(img, tile, x, y, height, (50, 50, 50));
+    draw_vertical_line(img, tile, x + width, y, height, (50, 50, 50));
+    draw_horizontal_line(img, tile, x, y, width, (50, 50, 50));
+    draw_horizontal_line(img, tile, x, y + height, width, (50, 50, 50));
+  }
+}
+
+fn create_image(file_name: String, container: &Container, items: &[Item], layout: &Layout, container_idx: usize) {
   let container_width: i64 = container.width;
   let container_height: i64 = container.height;
+  let tile: f64 = tile_size(container_width, container_height);
 
-  let mut img = RgbImage::new((container_width * TILE_SIZE) as u32 + 1, (container_height * TILE_SIZE) as u32 + 1);
+  let mut img = RgbImage::new(to_pixel(container_width, tile) + 1, to_pixel(container_height, tile) + 1);
 
-  for i in 0..container.width + 1 {
-    draw_vertical_line(&mut img, i, 0, container_height, (50, 50, 50));
+  draw_border(&mut img, tile, 0, 0, container_width, container_height);
+
+  for outside in &container.outside {
+    draw_background(&mut img, tile, outside.col, outside.row, outside.width, outside.height, (235, 235, 235));
   }
 
-  for i in 0..container.height + 1 {
-    draw_horizontal_line(&mut img, 0, i, container_width, (50, 50, 50));
+  draw_spacing(&mut img, tile, container, items, layout, container_idx);
+
+  for obstacle in &container.obstacles {
+    draw_background(&mut img, tile, obstacle.col, obstacle.row, obstacle.width, obstacle.height, (110, 110, 110));
+  }
+
+  draw_solution(&mut img, tile, items, layout, container_idx);
+
+  img.save(file_name).unwrap();
+}
+
+// Draws every layer of a box container seen from above, side by side in rows
+// (bottom layer first), leaving one empty cell between them.
+fn create_layer_image(file_name: String, container: &BoxContainer, items: &[BoxItem], layout: &BoxLayout, container_idx: usize) {
+  let layer_count = std::cmp::max(1, layout.layers[container_idx].len()) as i64;
+  let columns = (layer_count as f64).sqrt().ceil() as i64;
+  let rows = (layer_count + columns - 1) / columns;
+
+  let image_width = columns * (container.width + 1) - 1;
+  let image_height = rows * (container.depth + 1) - 1;
+  let tile: f64 = tile_size(image_width, image_height);
+
+  let mut img = RgbImage::new(to_pixel(image_width, tile) + 1, to_pixel(image_height, tile) + 1);
+
+  let layer_origin = |layer_idx: usize| {
+    let layer_idx = layer_idx as i64;
+    ((layer_idx % columns) * (container.width + 1), (layer_idx / columns) * (container.depth + 1))
+  };
+
+  for layer_idx in 0..layer_count as usize {
+    let (x, y) = layer_origin(layer_idx);
+    draw_border(&mut img, tile, x, y, container.width, container.depth);
   }
 
-  draw_solution(&mut img, container, items, solution);
+  for placed in layout.placed_boxes.iter().filter(|placed| placed.container_idx == container_idx) {
+    let (x, y) = layer_origin(placed.layer_idx);
+    let (width, depth, _) = items[placed.item_idx].size(placed.orientation);
+    draw_rectangle(&mut img, tile, x + placed.col, y + placed.row, width, depth, (193, 101, 10));
+  }
 
   img.save(file_name).unwrap();
 }
+
+fn image_file_name(name: &str, container_count: usize, container_idx: usize) -> String {
+  if container_count == 1 {
+    format!("{}.png", name)
+  } else {
+    format!("{}_{}.png", name, container_idx + 1)
+  }
+}
+
+// Creates one image per box container, showing its layers.
+pub fn create_layer_images(name: &str, containers: &[BoxContainer], items: &[BoxItem], solution: &[i64], decoder: &LayerDecoder) {
+  let mut spaces = decoder.new_spaces(containers);
+  let layout = decoder.decode(containers, &mut spaces, items, solution);
+
+  for (container_idx, container) in containers.iter().enumerate() {
+    create_layer_image(image_file_name(name, containers.len(), container_idx), container, items, &layout, container_idx);
+  }
+}
+
+// Creates one image per container. With a single container the file is named "<name>.png",
+// otherwise "<name>_1.png", "<name>_2.png", etc.
+pub fn create_images(name: &str, containers: &[Container], items: &[Item], solution: &[i64], decoder: &Decoder) {
+  let mut spaces = decoder.new_spaces(containers);
+  let layout = decoder.decode(containers, &mut spaces, items, solution);
+
+  for (container_idx, container) in containers.iter().enumerate() {
+    create_image(image_file_name(name, containers.len(), container_idx), container, items, &layout, container_idx);
+  }
+}
@@ -0,0 +1,92 @@
+use super::genetic_algorithm::{Evolvable, GaConfig, GeneticAlgorithm};
+use super::types::stats::Stats;
+use crossbeam::channel::{self, Receiver, Sender};
+use super::math;
+use rand::Rng;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Copies of the best solutions of an island, sent to another island.
+type Migrants = Vec<Vec<i64>>;
+
+// Where each island sends its migrants.
+#[derive(Copy, Clone)]
+pub enum Topology {
+  // To the next island, and the last one to the first.
+  Ring,
+  // To a different island picked at random every time.
+  Random
+}
+
+pub static NAMES: [&str; 2] = ["ring", "random"];
+
+impl Topology {
+  pub fn from_name(name: &str) -> Option<Topology> {
+    match name {
+      "ring" => Some(Topology::Ring),
+      "random" => Some(Topology::Random),
+      _ => None
+    }
+  }
+
+  fn target(&self, island_idx: usize, island_count: usize) -> usize {
+    match self {
+      Topology::Ring => (island_idx + 1) % island_count,
+      Topology::Random => {
+        // Skips this island.
+        let target = math::rng().gen_range(0..island_count - 1);
+        if target >= island_idx { target + 1 } else { target }
+      }
+    }
+  }
+}
+
+// Island model: every population evolves on its own thread, with its own stats. Every
+// `migration_interval` generations each island sends copies of its best solutions to another island,
+// where they replace part of the next generation. The shared stats keep the best of all islands.
+// Runs until an island finds the global optimum, or `term_now` is set.
+pub fn run<I: Evolvable>(islands: Vec<GeneticAlgorithm<I>>, stats: &mut Stats, config: &GaConfig, term_now: &AtomicBool) {
+  let (topology, migration_interval) = (config.migration_topology, config.migration_interval);
+  let island_count = islands.len();
+  let (senders, receivers): (Vec<Sender<Migrants>>, Vec<Receiver<Migrants>>) = (0..island_count).map(|_| channel::unbounded()).unzip();
+  let shared_stats = Mutex::new(stats.clone());
+  let global_optimum_found = AtomicBool::new(false);
+
+  crossbeam::scope(|scope| {
+    for (island_idx, (mut genetic_algorithm, receiver)) in islands.into_iter().zip(receivers).enumerate() {
+      let (senders, shared_stats, global_optimum_found) = (&senders, &shared_stats, &global_optimum_found);
+      let mut island_stats = stats.clone();
+
+      scope.spawn(move |_| {
+        // Each island gets its own seed, so they don't evolve the same way.
+        if let Some(seed) = config.seed {
+          math::seed_rng(seed.wrapping_add(island_idx as u64 + 1));
+        }
+
+        while !term_now.load(Ordering::Relaxed) && !global_optimum_found.load(Ordering::Relaxed) {
+          let found = genetic_algorithm.execute_population(&mut island_stats);
+          genetic_algorithm.merge_stats(&mut shared_stats.lock().unwrap(), &island_stats);
+          if found {
+            global_optimum_found.store(true, Ordering::Relaxed);
+            break;
+          }
+
+          if island_count > 1 && island_stats.total_generations % migration_interval == 0 {
+            let target = topology.target(island_idx, island_count);
+            // The target island may have stopped already, in which case the migrants are lost.
+            senders[target].send(genetic_algorithm.emigrants()).ok();
+          }
+          while let Ok(migrants) = receiver.try_recv() {
+            genetic_algorithm.immigrate(migrants);
+          }
+        }
+        genetic_algorithm.add_cache_stats(&mut shared_stats.lock().unwrap());
+      });
+    }
+  }).expect("islands did not complete successfully");
+
+  if global_optimum_found.load(Ordering::Relaxed) {
+    println!("Global optimum found.");
+  }
+  *stats = shared_stats.into_inner().unwrap();
+}
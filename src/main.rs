@@ -1,25 +1,37 @@
 extern crate clap;
-extern crate text_io;
 
 mod types;
 mod math;
+mod gene;
 mod genetic_algorithm;
 mod image_writer;
 mod util;
 mod dataset_loader;
+mod placement;
+mod scaling;
+mod selection;
+mod crossover;
+mod mutation;
+mod pareto;
+mod objective;
+mod island;
+mod fitness_cache;
+mod evaluator_pool;
+mod decoder;
+mod box_decoder;
+mod space;
 
-use genetic_algorithm::GeneticAlgorithm;
-use types::item::Item;
-use types::container::Container;
+use genetic_algorithm::{Evolvable, Evaluator, GaConfig, GeneticAlgorithm};
 use types::stats::Stats;
+use dataset_loader::Scenario;
+use decoder::LayoutEvaluator;
+use box_decoder::BoxEvaluator;
 use std::io::Error;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
-use std::sync::atomic::Ordering;
 use signal_hook::flag;
 use signal_hook::consts::TERM_SIGNALS;
 use signal_hook::iterator::Signals;
-use crossbeam;
 
 fn set_signal_handler(term_now: &Arc<AtomicBool>) -> Result<(), Error> {
   let mut signals: Signals = Signals::new(TERM_SIGNALS)?;
@@ -30,50 +42,90 @@ fn set_signal_handler(term_now: &Arc<AtomicBool>) -> Result<(), Error> {
     // in the generations loop, and we press CTRL+C several times, it would not exit.
     // Having this line "forces" the termination when executing the second termination.
     // More info: https://docs.rs/signal-hook/0.3.10/signal_hook/#a-complex-signal-handling-with-a-background-thread
-    flag::register_conditional_shutdown(*sig, 1, Arc::clone(&term_now))?;
-    flag::register(*sig, Arc::clone(&term_now))?;
+    flag::register_conditional_shutdown(*sig, 1, Arc::clone(term_now))?;
+    flag::register(*sig, Arc::clone(term_now))?;
   }
-  for signal in &mut signals {
-    eprintln!("\nReceived a signal {:?}", signal);
-    match signal {
-      term_sig => {
-        eprintln!("\nTerminating");
-        assert!(TERM_SIGNALS.contains(&term_sig));
-        break;
-      }
-    }
+  if let Some(term_sig) = signals.forever().next() {
+    eprintln!("\nReceived a signal {:?}", term_sig);
+    eprintln!("\nTerminating");
+    assert!(TERM_SIGNALS.contains(&term_sig));
   }
 
   Ok(())
 }
 
-fn main() -> Result<(), Error> {
-  let (container, items): (Container, Vec<Item>) = dataset_loader::build_scenario_from_opts()?;
-  let mut genetic_algorithm: GeneticAlgorithm = GeneticAlgorithm::new(container, &items);
-  let mut stats: Stats = Stats::new(&items);
-  let term_now = Arc::new(AtomicBool::new(false));
+// One population per island, each with its own evaluators (and scratch room), one per evaluation thread.
+fn build_islands<I: Evolvable>(items: &[I], config: &GaConfig, new_evaluator: impl Fn() -> Box<dyn Evaluator>) -> Vec<GeneticAlgorithm<I>> {
+  (0..config.islands).map(|island_idx| {
+    let evaluators = (0..config.threads).map(|_| new_evaluator()).collect();
+    let genetic_algorithm = GeneticAlgorithm::new(items, evaluators, config.clone());
+    if config.islands > 1 { genetic_algorithm.with_island_index(island_idx) } else { genetic_algorithm }
+  }).collect()
+}
 
-  println!("Items: {}", items.len());
-  println!("Max score assuming infinite container: {}", stats.max_possible_score);
+// Runs generations until the global optimum is found or the program is stopped,
+// then prints the stats and calls finish with them.
+fn run<I: Evolvable>(islands: Vec<GeneticAlgorithm<I>>, config: &GaConfig, mut stats: Stats, finish: impl FnOnce(&Stats) + Send) {
+  let term_now = Arc::new(AtomicBool::new(false));
 
   crossbeam::scope(|scope| {
     scope.spawn(|_| {
-      while !term_now.load(Ordering::Relaxed){
-        let global_optimum_found: bool = genetic_algorithm.execute_population(&mut stats);
-        if global_optimum_found {
-          println!("Global optimum found.");
-          break;
-        }
-      }
+      island::run(islands, &mut stats, config, &term_now);
 
       stats.print();
-      image_writer::create_image("output.png".to_string(), &container, &items, &stats.optimal_solution);
+      finish(&stats);
     });
 
     scope.spawn(|_| {
       set_signal_handler(&term_now).unwrap();
     });
   }).expect("threads did not complete successfully");
+}
+
+// One image per point of the Pareto front, numbered as in the stats.
+fn pareto_image_name(idx: usize) -> String {
+  format!("output_front_{}", idx + 1)
+}
+
+fn main() -> Result<(), Error> {
+  let (scenario, ga_config) = dataset_loader::build_scenario_from_opts()?;
+
+  match scenario {
+    Scenario::Flat(containers, items, decoder) => {
+      let islands = build_islands(&items, &ga_config, || Box::new(LayoutEvaluator::new(decoder.clone(), &containers, &items)));
+      let stats: Stats = Stats::new(&items, containers.iter().map(|container| container.max_weight).collect());
+
+      println!("Containers: {}", containers.len());
+      println!("Items: {} ({} counting every copy)", items.len(), items.iter().map(|item| item.quantity).sum::<i64>());
+      println!("Max score assuming infinite container: {}", stats.max_possible_score);
+
+      run(islands, &ga_config, stats, |stats| {
+        let mut spaces = decoder.new_spaces(&containers);
+        decoder.decode(&containers, &mut spaces, &items, &stats.optimal_solution).print_by_item_type(&items);
+        image_writer::create_images("output", &containers, &items, &stats.optimal_solution, &decoder);
+        for (idx, point) in stats.pareto_front.iter().enumerate() {
+          image_writer::create_images(&pareto_image_name(idx), &containers, &items, &point.solution, &decoder);
+        }
+      });
+    },
+    Scenario::Boxes(containers, items, decoder) => {
+      let islands = build_islands(&items, &ga_config, || Box::new(BoxEvaluator::new(decoder.clone(), &containers, &items)));
+      let stats: Stats = Stats::new(&items, containers.iter().map(|container| container.max_weight).collect());
+
+      println!("Containers: {}", containers.len());
+      println!("Boxes: {} ({} counting every copy)", items.len(), items.iter().map(|item| item.quantity).sum::<i64>());
+      println!("Max score assuming infinite container: {}", stats.max_possible_score);
+
+      run(islands, &ga_config, stats, |stats| {
+        let mut spaces = decoder.new_spaces(&containers);
+        decoder.decode(&containers, &mut spaces, &items, &stats.optimal_solution).print_by_item_type(&items);
+        image_writer::create_layer_images("output", &containers, &items, &stats.optimal_solution, &decoder);
+        for (idx, point) in stats.pareto_front.iter().enumerate() {
+          image_writer::create_layer_images(&pareto_image_name(idx), &containers, &items, &point.solution, &decoder);
+        }
+      });
+    }
+  }
 
   Ok(())
 }
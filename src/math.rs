@@ -1,26 +1,70 @@
-use rand::Rng;
+use rand::{Rng, RngCore, SeedableRng};
+use rand::rngs::StdRng;
+use std::cell::RefCell;
+
+thread_local! {
+  static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+// Random numbers for the whole program. Each thread has its own generator, seeded by the OS
+// unless seed_rng is called, so a run can be repeated with the same seed (see --seed).
+pub struct SeededRng;
+
+impl RngCore for SeededRng {
+  fn next_u32(&mut self) -> u32 {
+    RNG.with(|rng| rng.borrow_mut().next_u32())
+  }
+
+  fn next_u64(&mut self) -> u64 {
+    RNG.with(|rng| rng.borrow_mut().next_u64())
+  }
+
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    RNG.with(|rng| rng.borrow_mut().fill_bytes(dest))
+  }
+
+  fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+    RNG.with(|rng| rng.borrow_mut().try_fill_bytes(dest))
+  }
+}
+
+pub fn rng() -> SeededRng {
+  SeededRng
+}
+
+// Seeds the generator of the current thread.
+pub fn seed_rng(seed: u64) {
+  RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
 
 pub fn probability(n: f32) -> bool {
-  rand::thread_rng().gen::<f32>() <= n
+  rng().gen::<f32>() <= n
+}
+
+// Start and end (exclusive) of a random segment of a list with the given length, possibly empty.
+pub fn random_segment(len: usize) -> (usize, usize) {
+  let mut rng = rng();
+  let a = rng.gen_range(0..=len);
+  let b = rng.gen_range(0..=len);
+  (std::cmp::min(a, b), std::cmp::max(a, b))
 }
 
-pub fn mean(scores: &Vec<i64>) -> f64 {
+pub fn mean(scores: &[f64]) -> f64 {
   let mut sum: f64 = 0.0;
-  for i in 0..scores.len() {
-    let n = scores[i] as f64;
-    sum += n;
+  for &score in scores {
+    sum += score;
   }
   sum / (scores.len() as f64)
 }
 
-pub fn standard_deviation(scores: &Vec<i64>) -> f64 {
+pub fn standard_deviation(scores: &[f64]) -> f64 {
   let mut standard_deviation: f64 = 0.0;
   let mean: f64 = mean(scores);
 
-  for i in 0..scores.len() {
-    let n = scores[i] as f64;
+  for &score in scores {
+    let n = score;
     standard_deviation += (n - mean) * (n - mean);
   }
 
-  return (standard_deviation / (scores.len() as f64)).sqrt();
+  (standard_deviation / (scores.len() as f64)).sqrt()
 }
@@ -0,0 +1,106 @@
+use super::math;
+use super::gene;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::sync::Arc;
+
+// Changes a solution in place, keeping the same genes (only their order and orientations change).
+// `gene_probability` is the chance of changing each gene, for the operators that go gene by gene.
+// `reorient` returns the gene with a random orientation of its item, or None if the item has only one.
+pub trait Mutation: Send + Sync {
+  fn mutate(&self, list: &mut [i64], gene_probability: f32, reorient: &dyn Fn(i64) -> Option<i64>);
+}
+
+pub static NAMES: [&str; 5] = ["legacy", "insertion", "inversion", "scramble", "rotation-flip"];
+
+pub fn from_name(name: &str) -> Option<Arc<dyn Mutation>> {
+  match name {
+    "legacy" => Some(Arc::new(Legacy)),
+    "insertion" => Some(Arc::new(Insertion)),
+    "inversion" => Some(Arc::new(Inversion)),
+    "scramble" => Some(Arc::new(Scramble)),
+    "rotation-flip" => Some(Arc::new(RotationFlip)),
+    _ => None
+  }
+}
+
+// Goes gene by gene, either reorienting the gene or swapping it with a random gene after it.
+pub struct Legacy;
+
+// Moves a random gene to a random position.
+pub struct Insertion;
+
+// Reverses the order of a random segment.
+pub struct Inversion;
+
+// Shuffles a random segment.
+pub struct Scramble;
+
+// Goes gene by gene, giving the item another orientation (rotated, flipped or tilted, depending on the item).
+pub struct RotationFlip;
+
+impl Mutation for Legacy {
+  fn mutate(&self, list: &mut [i64], gene_probability: f32, reorient: &dyn Fn(i64) -> Option<i64>) {
+    for i in 0..list.len() {
+      if !math::probability(gene_probability) { continue; }
+
+      // Sometimes turn (or flip) the item instead of moving it.
+      if math::probability(0.5) {
+        if let Some(reoriented) = reorient(list[i]) {
+          list[i] = reoriented;
+          continue;
+        }
+      }
+
+      let range = (i + 1) as i64 .. list.len() as i64;
+      if range.is_empty() { continue; }
+      let idx: i64 = math::rng().gen_range(range);
+
+      // Swapping two copies of the same item does nothing, so swap with the next different item instead.
+      let different_idx = (idx as usize..list.len()).find(|&j| gene::item_index(list[j]) != gene::item_index(list[i]));
+      if let Some(j) = different_idx {
+        list.swap(i, j);
+      }
+    }
+  }
+}
+
+impl Mutation for Insertion {
+  fn mutate(&self, list: &mut [i64], _gene_probability: f32, _reorient: &dyn Fn(i64) -> Option<i64>) {
+    if list.len() < 2 { return; }
+
+    let mut rng = math::rng();
+    let from = rng.gen_range(0..list.len());
+    let to = rng.gen_range(0..list.len());
+    if from < to {
+      list[from..=to].rotate_left(1);
+    } else {
+      list[to..=from].rotate_right(1);
+    }
+  }
+}
+
+impl Mutation for Inversion {
+  fn mutate(&self, list: &mut [i64], _gene_probability: f32, _reorient: &dyn Fn(i64) -> Option<i64>) {
+    let (start, end) = math::random_segment(list.len());
+    list[start..end].reverse();
+  }
+}
+
+impl Mutation for Scramble {
+  fn mutate(&self, list: &mut [i64], _gene_probability: f32, _reorient: &dyn Fn(i64) -> Option<i64>) {
+    let (start, end) = math::random_segment(list.len());
+    list[start..end].shuffle(&mut math::rng());
+  }
+}
+
+impl Mutation for RotationFlip {
+  fn mutate(&self, list: &mut [i64], gene_probability: f32, reorient: &dyn Fn(i64) -> Option<i64>) {
+    for item_gene in list.iter_mut() {
+      if !math::probability(gene_probability) { continue; }
+      if let Some(reoriented) = reorient(*item_gene) {
+        *item_gene = reoriented;
+      }
+    }
+  }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+// What the genetic algorithm maximizes. Solutions are ranked by their value, and solutions with
+// the same value by the least wasted room (unless the objective ignores it).
+// The benefit is already penalized when required items are missing (see genetic_algorithm::required_penalty).
+pub trait Objective: Send + Sync {
+  // `used` is the area (or volume, for boxes) covered by the placed items.
+  fn value(&self, benefit: i64, wasted: i64, used: i64) -> f64;
+
+  // False to leave solutions with the same value in any order.
+  fn breaks_ties_by_waste(&self) -> bool {
+    true
+  }
+
+  // True when placing every item's benefit (and every required item) means that no solution
+  // can have a better value, so the search stops there.
+  fn stops_at_max_benefit(&self) -> bool {
+    false
+  }
+}
+
+pub static NAMES: [&str; 4] = ["benefit", "benefit-then-waste", "weighted", "benefit-per-area"];
+
+pub fn from_name(name: &str, waste_weight: f64) -> Option<Arc<dyn Objective>> {
+  match name {
+    "benefit" => Some(Arc::new(Benefit)),
+    "benefit-then-waste" => Some(Arc::new(BenefitThenWaste)),
+    "weighted" => Some(Arc::new(Weighted { waste_weight })),
+    "benefit-per-area" => Some(Arc::new(BenefitPerArea)),
+    _ => None
+  }
+}
+
+// Only the benefit counts.
+pub struct Benefit;
+
+// The benefit, and the least wasted room between solutions with the same benefit.
+pub struct BenefitThenWaste;
+
+// The benefit minus `waste_weight` for each unit of wasted room.
+pub struct Weighted {
+  pub waste_weight: f64
+}
+
+// The benefit of each unit of room covered by items. Layouts without items are worth their (penalized) benefit.
+pub struct BenefitPerArea;
+
+impl Objective for Benefit {
+  fn value(&self, benefit: i64, _wasted: i64, _used: i64) -> f64 {
+    benefit as f64
+  }
+
+  fn breaks_ties_by_waste(&self) -> bool {
+    false
+  }
+
+  fn stops_at_max_benefit(&self) -> bool {
+    true
+  }
+}
+
+impl Objective for BenefitThenWaste {
+  fn value(&self, benefit: i64, _wasted: i64, _used: i64) -> f64 {
+    benefit as f64
+  }
+
+  fn stops_at_max_benefit(&self) -> bool {
+    true
+  }
+}
+
+impl Objective for Weighted {
+  fn value(&self, benefit: i64, wasted: i64, _used: i64) -> f64 {
+    benefit as f64 - self.waste_weight * wasted as f64
+  }
+}
+
+impl Objective for BenefitPerArea {
+  fn value(&self, benefit: i64, _wasted: i64, used: i64) -> f64 {
+    if used == 0 { benefit as f64 } else { benefit as f64 / used as f64 }
+  }
+}
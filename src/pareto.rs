@@ -0,0 +1,182 @@
+// Multi-objective helpers (NSGA-II). Each point is a (benefit, wasted room) score:
+// the benefit is maximized and the wasted room minimized.
+
+// True if `a` is at least as good as `b` in both objectives, and better in one of them.
+pub fn dominates(a: (i64, i64), b: (i64, i64)) -> bool {
+  a.0 >= b.0 && a.1 <= b.1 && a != b
+}
+
+// Fast non-dominated sort. The first front has the points that no other point dominates,
+// the second front the points only dominated by the first front, and so on.
+pub fn non_dominated_fronts(points: &[(i64, i64)]) -> Vec<Vec<usize>> {
+  let mut dominated_by_count = vec![0; points.len()];
+  let mut dominated = vec![Vec::<usize>::new(); points.len()];
+  let mut fronts = Vec::<Vec<usize>>::new();
+  let mut front = Vec::<usize>::new();
+
+  for i in 0..points.len() {
+    for j in 0..points.len() {
+      if dominates(points[i], points[j]) {
+        dominated[i].push(j);
+      } else if dominates(points[j], points[i]) {
+        dominated_by_count[i] += 1;
+      }
+    }
+    if dominated_by_count[i] == 0 {
+      front.push(i);
+    }
+  }
+
+  while !front.is_empty() {
+    let mut next = Vec::<usize>::new();
+    for &i in &front {
+      for &j in &dominated[i] {
+        dominated_by_count[j] -= 1;
+        if dominated_by_count[j] == 0 {
+          next.push(j);
+        }
+      }
+    }
+    fronts.push(front);
+    front = next;
+  }
+
+  fronts
+}
+
+// How far each point of a front is from its neighbours, summed over both objectives and
+// normalized by the range of each objective. The points at the ends of the front get an
+// infinite distance, so they're always kept.
+pub fn crowding_distances(points: &[(i64, i64)], front: &[usize]) -> Vec<f64> {
+  let mut distances = vec![0.0; front.len()];
+  let objectives: [fn((i64, i64)) -> i64; 2] = [|point| point.0, |point| point.1];
+
+  for objective in objectives.iter() {
+    let mut order: Vec<usize> = (0..front.len()).collect();
+    order.sort_by_key(|&idx| objective(points[front[idx]]));
+
+    let (first, last) = match (order.first(), order.last()) {
+      (Some(&first), Some(&last)) => (first, last),
+      _ => continue
+    };
+    distances[first] = f64::INFINITY;
+    distances[last] = f64::INFINITY;
+
+    let range = (objective(points[front[last]]) - objective(points[front[first]])) as f64;
+    if range == 0.0 { continue; }
+
+    for window in order.windows(3) {
+      let gap = (objective(points[front[window[2]]]) - objective(points[front[window[0]]])) as f64;
+      distances[window[1]] += gap / range;
+    }
+  }
+
+  distances
+}
+
+// Indexes of the points sorted by NSGA-II's crowded comparison: by front first,
+// and within a front by crowding distance, largest first.
+pub fn crowded_order(points: &[(i64, i64)]) -> Vec<usize> {
+  let mut order = Vec::<usize>::new();
+
+  for front in non_dominated_fronts(points) {
+    let distances = crowding_distances(points, &front);
+    let mut by_distance: Vec<usize> = (0..front.len()).collect();
+    by_distance.sort_by(|&a, &b| distances[b].partial_cmp(&distances[a]).unwrap_or(std::cmp::Ordering::Equal));
+    order.extend(by_distance.into_iter().map(|idx| front[idx]));
+  }
+
+  order
+}
+
+// A solution of the Pareto front kept in the stats.
+#[derive(Clone)]
+pub struct ParetoPoint {
+  pub benefit: i64,
+  pub wasted: i64,
+  pub solution: Vec<i64>
+}
+
+// Adds the solution to the front unless a point of the front dominates (or matches) it,
+// and removes the points it dominates. The front is kept sorted by benefit, highest first.
+pub fn update_front(front: &mut Vec<ParetoPoint>, benefit: i64, wasted: i64, solution: &[i64]) {
+  let candidate = (benefit, wasted);
+  if front.iter().any(|point| point_score(point) == candidate || dominates(point_score(point), candidate)) {
+    return;
+  }
+
+  front.retain(|point| !dominates(candidate, point_score(point)));
+  front.push(ParetoPoint { benefit, wasted, solution: solution.to_vec() });
+  front.sort_by_key(|point| std::cmp::Reverse(point.benefit));
+}
+
+fn point_score(point: &ParetoPoint) -> (i64, i64) {
+  (point.benefit, point.wasted)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // (benefit, wasted room) of each point.
+  fn points() -> Vec<(i64, i64)> {
+    vec![(10, 5), (8, 2), (6, 1), (9, 6), (7, 4), (5, 7), (10, 5)]
+  }
+
+  #[test]
+  fn dominance() {
+    assert!(dominates((10, 5), (9, 6)));
+    assert!(dominates((10, 5), (10, 6)));
+    assert!(!dominates((10, 5), (10, 5)));
+    assert!(!dominates((10, 5), (8, 2)));
+  }
+
+  #[test]
+  fn fronts_are_ranked() {
+    let mut fronts = non_dominated_fronts(&points());
+    for front in fronts.iter_mut() {
+      front.sort_unstable();
+    }
+    assert_eq!(fronts, vec![vec![0, 1, 2, 6], vec![3, 4], vec![5]]);
+  }
+
+  #[test]
+  fn boundaries_have_infinite_crowding_distance() {
+    let points = vec![(10, 9), (8, 6), (7, 4), (4, 2), (1, 0)];
+    let front: Vec<usize> = (0..points.len()).collect();
+    let distances = crowding_distances(&points, &front);
+
+    assert_eq!(distances[0], f64::INFINITY);
+    assert_eq!(distances[4], f64::INFINITY);
+    // (10 - 7) / 9 + (9 - 4) / 9
+    assert!((distances[1] - 8.0 / 9.0).abs() < 1e-9);
+    assert!(distances[1..4].iter().all(|distance| distance.is_finite()));
+  }
+
+  #[test]
+  fn crowded_order_goes_front_by_front() {
+    let order = crowded_order(&points());
+    assert_eq!(order.len(), 7);
+    let mut first: Vec<usize> = order[..4].to_vec();
+    first.sort_unstable();
+    assert_eq!(first, vec![0, 1, 2, 6]);
+    assert_eq!(order[6], 5);
+  }
+
+  #[test]
+  fn update_front_drops_dominated_points_and_duplicates() {
+    let mut front = Vec::<ParetoPoint>::new();
+    update_front(&mut front, 8, 4, &[1]);
+    update_front(&mut front, 6, 1, &[2]);
+    // Duplicate of a point already in the front.
+    update_front(&mut front, 8, 4, &[3]);
+    // Dominated by (8, 4).
+    update_front(&mut front, 7, 5, &[4]);
+    assert_eq!(front.iter().map(point_score).collect::<Vec<_>>(), vec![(8, 4), (6, 1)]);
+
+    // Dominates (8, 4), which is dropped.
+    update_front(&mut front, 9, 3, &[5]);
+    assert_eq!(front.iter().map(point_score).collect::<Vec<_>>(), vec![(9, 3), (6, 1)]);
+    assert_eq!(front[0].solution, vec![5]);
+  }
+}
@@ -0,0 +1,84 @@
+use super::space::FreeSpace;
+use super::types::shape::Shape;
+use std::sync::Arc;
+
+// Decides where an item goes inside the container, given the room that is still free.
+// Every placement has to be deterministic, since the score of a solution and its
+// rendered image must be computed with exactly the same layout.
+pub trait Placement: Send + Sync {
+  fn find_position(&self, space: &dyn FreeSpace, shape: &Shape) -> Option<(i64, i64)>;
+}
+
+pub static NAMES: [&str; 4] = ["top-left", "bottom-left-fill", "contact-perimeter", "min-waste"];
+
+pub fn from_name(name: &str) -> Option<Arc<dyn Placement>> {
+  match name {
+    "top-left" => Some(Arc::new(TopLeft)),
+    "bottom-left-fill" => Some(Arc::new(BottomLeftFill)),
+    "contact-perimeter" => Some(Arc::new(ContactPerimeter)),
+    "min-waste" => Some(Arc::new(MinWaste)),
+    _ => None
+  }
+}
+
+// Scans the top row first, then left to right.
+pub struct TopLeft;
+
+// Scans the bottom row first, then left to right.
+pub struct BottomLeftFill;
+
+// Picks the position where the item touches the most filled cells or container borders.
+pub struct ContactPerimeter;
+
+// Picks the position that leaves the fewest empty cells trapped above the item.
+pub struct MinWaste;
+
+fn first_position(space: &dyn FreeSpace, shape: &Shape, bottom_up: bool) -> Option<(i64, i64)> {
+  let mut found: Option<(i64, i64)> = None;
+  space.visit_positions(shape, bottom_up, &mut |row, col| {
+    found = Some((row, col));
+    false
+  });
+  found
+}
+
+// Returns the position with the lowest cost. Ties go to the first position scanned: top-left order,
+// or bottom-left order in stability mode, where positions are scanned bottom-up (see decoder::ConstrainedSpace).
+fn best_position<F: Fn(i64, i64) -> i64>(space: &dyn FreeSpace, shape: &Shape, cost: F) -> Option<(i64, i64)> {
+  let mut best: Option<(i64, (i64, i64))> = None;
+
+  space.visit_positions(shape, false, &mut |row, col| {
+    let position_cost = cost(row, col);
+    match best {
+      Some((best_cost, _)) if best_cost <= position_cost => {},
+      _ => best = Some((position_cost, (row, col)))
+    }
+    true
+  });
+
+  best.map(|(_, position)| position)
+}
+
+impl Placement for TopLeft {
+  fn find_position(&self, space: &dyn FreeSpace, shape: &Shape) -> Option<(i64, i64)> {
+    first_position(space, shape, false)
+  }
+}
+
+impl Placement for BottomLeftFill {
+  fn find_position(&self, space: &dyn FreeSpace, shape: &Shape) -> Option<(i64, i64)> {
+    first_position(space, shape, true)
+  }
+}
+
+impl Placement for ContactPerimeter {
+  fn find_position(&self, space: &dyn FreeSpace, shape: &Shape) -> Option<(i64, i64)> {
+    best_position(space, shape, |row, col| -space.shape_contact_perimeter(row, col, shape))
+  }
+}
+
+impl Placement for MinWaste {
+  fn find_position(&self, space: &dyn FreeSpace, shape: &Shape) -> Option<(i64, i64)> {
+    best_position(space, shape, |row, col| space.shape_trapped_cells_above(row, col, shape))
+  }
+}
@@ -0,0 +1,106 @@
+use super::math;
+use std::sync::Arc;
+
+// Turns the scores (objective values) of a generation into the fitness values used by the selection.
+// Scores are sorted from best to worst, and so are the fitness values.
+// Late in a run the scores are close together, and scaling keeps the best solutions ahead.
+pub trait Scaling: Send + Sync {
+  fn scale(&self, scores: &[f64]) -> Vec<f64>;
+}
+
+pub static NAMES: [&str; 5] = ["none", "linear", "sigma", "rank", "power"];
+
+pub fn from_name(name: &str, factor: f64) -> Option<Arc<dyn Scaling>> {
+  match name {
+    "none" => Some(Arc::new(Identity)),
+    "linear" => Some(Arc::new(Linear { factor })),
+    "sigma" => Some(Arc::new(Sigma { factor })),
+    "rank" => Some(Arc::new(Rank)),
+    "power" => Some(Arc::new(Power { exponent: factor })),
+    _ => None
+  }
+}
+
+// The fitness is the score.
+pub struct Identity;
+
+// Keeps the average, and stretches the scores so the best fitness is `factor` times the average.
+// When that would make the worst fitness negative, it's stretched so the worst fitness is 0 instead.
+pub struct Linear {
+  pub factor: f64
+}
+
+// Sigma truncation: subtracts the average minus `factor` standard deviations from the scores.
+// Scores below that get a fitness of 0.
+pub struct Sigma {
+  pub factor: f64
+}
+
+// The fitness only depends on the position in the ranking: 1 / sqrt(position), starting at 1.
+// Solutions with the same score share the same position.
+pub struct Rank;
+
+// Raises the scores to `exponent`.
+pub struct Power {
+  pub exponent: f64
+}
+
+// Scores can be negative, e.g. for layouts missing required items. Linear and power scaling
+// need non-negative values, so in that case everything is shifted to make the worst score 0.
+fn non_negative(scores: &[f64]) -> Vec<f64> {
+  let worst = scores.iter().cloned().fold(0.0, f64::min);
+  scores.iter().map(|&score| score - worst).collect()
+}
+
+impl Scaling for Identity {
+  fn scale(&self, scores: &[f64]) -> Vec<f64> {
+    scores.to_vec()
+  }
+}
+
+impl Scaling for Linear {
+  fn scale(&self, scores: &[f64]) -> Vec<f64> {
+    let values = non_negative(scores);
+    let mean = math::mean(&values);
+    let best = values.iter().cloned().fold(f64::MIN, f64::max);
+    let worst = values.iter().cloned().fold(f64::MAX, f64::min);
+
+    // All the scores are the same, so they all get the same chance.
+    if best <= mean {
+      return vec![1.0; values.len()];
+    }
+
+    let (a, b) = if worst > (self.factor * mean - best) / (self.factor - 1.0) {
+      ((self.factor - 1.0) * mean / (best - mean), mean * (best - self.factor * mean) / (best - mean))
+    } else {
+      (mean / (mean - worst), -worst * mean / (mean - worst))
+    };
+
+    values.iter().map(|&value| f64::max(0.0, a * value + b)).collect()
+  }
+}
+
+impl Scaling for Sigma {
+  fn scale(&self, scores: &[f64]) -> Vec<f64> {
+    let base = math::mean(scores) - self.factor * math::standard_deviation(scores);
+    scores.iter().map(|&value| f64::max(0.0, value - base)).collect()
+  }
+}
+
+impl Scaling for Rank {
+  fn scale(&self, scores: &[f64]) -> Vec<f64> {
+    let mut position = 0;
+    scores.iter().enumerate().map(|(idx, &score)| {
+      if idx == 0 || score != scores[idx - 1] {
+        position = idx + 1;
+      }
+      1.0 / (position as f64).sqrt()
+    }).collect()
+  }
+}
+
+impl Scaling for Power {
+  fn scale(&self, scores: &[f64]) -> Vec<f64> {
+    non_negative(scores).iter().map(|&value| value.powf(self.exponent)).collect()
+  }
+}
@@ -0,0 +1,99 @@
+use super::math;
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+use std::sync::Arc;
+
+// Picks the solutions that breed the next generation (the mating pool).
+// Fitness values (see scaling) are sorted from best to worst, and the returned values are indexes into them.
+// The same solution may be picked more than once.
+pub trait Selection: Send + Sync {
+  fn select(&self, fitness: &[f64], pool_size: usize) -> Vec<usize>;
+}
+
+pub static NAMES: [&str; 5] = ["legacy", "tournament", "roulette", "rank", "truncation"];
+
+pub fn from_name(name: &str, min_survivors: usize, tournament_size: usize, truncation_percent: f64) -> Option<Arc<dyn Selection>> {
+  match name {
+    "legacy" => Some(Arc::new(Legacy { min_survivors })),
+    "tournament" => Some(Arc::new(Tournament { size: tournament_size })),
+    "roulette" => Some(Arc::new(Roulette)),
+    "rank" => Some(Arc::new(Rank)),
+    "truncation" => Some(Arc::new(Truncation { percent: truncation_percent })),
+    _ => None
+  }
+}
+
+// Keeps every solution whose fitness is above the standard deviation of the fitness values,
+// and at least min_survivors of them.
+pub struct Legacy {
+  pub min_survivors: usize
+}
+
+// Each pick is the best of `size` random solutions.
+pub struct Tournament {
+  pub size: usize
+}
+
+// Fitness-proportional: each pick is random, weighted by the fitness. Negative values (layouts
+// missing required items) are shifted so the worst one is 0. Raw scores are often close
+// together, so this works best with a fitness scaling.
+pub struct Roulette;
+
+// Linear rank: each pick is random, weighted by the position in the ranking (best = population size, worst = 1).
+pub struct Rank;
+
+// Keeps the best `percent` of the solutions (at least one).
+pub struct Truncation {
+  pub percent: f64
+}
+
+// When every weight is 0, every solution gets the same chance.
+fn weighted_picks(weights: &[f64], pool_size: usize) -> Vec<usize> {
+  let mut rng = math::rng();
+  match WeightedIndex::new(weights) {
+    Ok(distribution) => (0..pool_size).map(|_| distribution.sample(&mut rng)).collect(),
+    Err(_) => (0..pool_size).map(|_| rng.gen_range(0..weights.len())).collect()
+  }
+}
+
+impl Selection for Legacy {
+  fn select(&self, fitness: &[f64], _pool_size: usize) -> Vec<usize> {
+    let stddev: f64 = math::standard_deviation(fitness);
+
+    // Ensure a minimum number of solutions.
+    (0..fitness.len()).take_while(|&idx| idx < self.min_survivors || fitness[idx] > stddev.trunc()).collect()
+  }
+}
+
+impl Selection for Tournament {
+  fn select(&self, fitness: &[f64], pool_size: usize) -> Vec<usize> {
+    let mut rng = math::rng();
+
+    // Fitness values are sorted, so the lowest index is the best contender.
+    (0..pool_size).map(|_| {
+      (0..self.size).map(|_| rng.gen_range(0..fitness.len())).min().unwrap_or(0)
+    }).collect()
+  }
+}
+
+impl Selection for Roulette {
+  fn select(&self, fitness: &[f64], pool_size: usize) -> Vec<usize> {
+    let worst = fitness.iter().cloned().fold(0.0, f64::min);
+    let weights: Vec<f64> = fitness.iter().map(|&value| value - worst).collect();
+    weighted_picks(&weights, pool_size)
+  }
+}
+
+impl Selection for Rank {
+  fn select(&self, fitness: &[f64], pool_size: usize) -> Vec<usize> {
+    let weights: Vec<f64> = (0..fitness.len()).map(|idx| (fitness.len() - idx) as f64).collect();
+    weighted_picks(&weights, pool_size)
+  }
+}
+
+impl Selection for Truncation {
+  fn select(&self, fitness: &[f64], _pool_size: usize) -> Vec<usize> {
+    let count = (fitness.len() as f64 * self.percent / 100.0).ceil() as usize;
+    (0..std::cmp::max(1, count)).collect()
+  }
+}
@@ -0,0 +1,104 @@
+use super::types::grid::OccupancyGrid;
+use super::types::free_rectangles::FreeRectangles;
+use super::types::container::Container;
+use super::types::shape::Shape;
+
+// Model of the free room inside a container, used by the decoder to place items.
+pub trait FreeSpace: Send {
+  // Empties the space, except for the regions of the container where items can't be placed.
+  fn reset(&mut self);
+
+  // Width and height of the container.
+  fn size(&self) -> (i64, i64);
+
+  // True if the rectangle is inside the container and doesn't overlap anything.
+  fn fits(&self, row: i64, col: i64, width: i64, height: i64) -> bool;
+
+  fn fill(&mut self, row: i64, col: i64, width: i64, height: i64);
+
+  fn count_free(&self) -> i64;
+
+  // Calls visit with the candidate positions (of the shape's top-left corner) where the shape fits,
+  // in scan order (top row first, or bottom row first, then left to right), until visit returns false.
+  fn visit_positions(&self, shape: &Shape, bottom_up: bool, visit: &mut dyn FnMut(i64, i64) -> bool);
+
+  // Same as visit_positions, for an item that must keep `spacing` cells clear around it. Models that
+  // only try some positions also try them moved away by the spacing, from the borders and other items.
+  fn visit_spaced_positions(&self, shape: &Shape, bottom_up: bool, _spacing: i64, visit: &mut dyn FnMut(i64, i64) -> bool) {
+    self.visit_positions(shape, bottom_up, visit);
+  }
+
+  // Length of the rectangle's border touching placed items or the container border.
+  fn contact_perimeter(&self, row: i64, col: i64, width: i64, height: i64) -> i64;
+
+  // Empty cells between the top edge of a horizontal segment and whatever is above it.
+  fn trapped_cells_above(&self, row: i64, col: i64, width: i64) -> i64;
+
+  // Filled cells right below a horizontal segment. Cells below the container count as filled (the floor).
+  fn filled_cells_below(&self, row: i64, col: i64, width: i64) -> i64;
+
+  fn fits_shape(&self, row: i64, col: i64, shape: &Shape) -> bool {
+    shape.rects.iter().all(|rect| self.fits(row + rect.row, col + rect.col, rect.width, rect.height))
+  }
+
+  fn fill_shape(&mut self, row: i64, col: i64, shape: &Shape) {
+    for rect in &shape.rects {
+      self.fill(row + rect.row, col + rect.col, rect.width, rect.height);
+    }
+  }
+
+  // The shape's own cells aren't filled yet, so borders between its rectangles never count as contact.
+  fn shape_contact_perimeter(&self, row: i64, col: i64, shape: &Shape) -> i64 {
+    if shape.is_rectangle() {
+      return self.contact_perimeter(row, col, shape.width, shape.height);
+    }
+    shape.rects.iter().map(|rect| self.contact_perimeter(row + rect.row, col + rect.col, rect.width, rect.height)).sum()
+  }
+
+  // A rectangle's top edge is its first row, so its profile isn't built.
+  fn shape_trapped_cells_above(&self, row: i64, col: i64, shape: &Shape) -> i64 {
+    if shape.is_rectangle() {
+      return self.trapped_cells_above(row, col, shape.width);
+    }
+    shape.top_profile().iter().map(|segment| self.trapped_cells_above(row + segment.row, col + segment.col, segment.width)).sum()
+  }
+
+  // Fraction of the shape's bottom edge resting on filled cells or the container floor.
+  fn shape_supported_fraction(&self, row: i64, col: i64, shape: &Shape) -> f64 {
+    if shape.is_rectangle() {
+      return self.filled_cells_below(row + shape.height - 1, col, shape.width) as f64 / shape.width as f64;
+    }
+    let bottom = shape.bottom_profile();
+    let supported: i64 = bottom.iter().map(|segment| self.filled_cells_below(row + segment.row, col + segment.col, segment.width)).sum();
+    let total: i64 = bottom.iter().map(|segment| segment.width).sum();
+    supported as f64 / total as f64
+  }
+}
+
+#[derive(Copy, Clone)]
+pub enum SpaceModel {
+  // One bit per cell. Fast for small containers, but the cost grows with the container area.
+  Grid,
+  // Maximal free rectangles. The cost grows with the number of items, so it works for large containers.
+  FreeRectangles
+}
+
+pub static NAMES: [&str; 2] = ["grid", "free-rectangles"];
+
+impl SpaceModel {
+  pub fn from_name(name: &str) -> Option<SpaceModel> {
+    match name {
+      "grid" => Some(SpaceModel::Grid),
+      "free-rectangles" => Some(SpaceModel::FreeRectangles),
+      _ => None
+    }
+  }
+
+  pub fn build(&self, container: &Container) -> Box<dyn FreeSpace> {
+    let (width, height) = (container.width, container.height);
+    match self {
+      SpaceModel::Grid => Box::new(OccupancyGrid::new(width, height, &container.blocked())),
+      SpaceModel::FreeRectangles => Box::new(FreeRectangles::new(width, height, &container.blocked()))
+    }
+  }
+}
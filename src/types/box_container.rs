@@ -0,0 +1,29 @@
+// A pallet, truck or any other container that holds boxes. The height is the vertical side.
+#[derive(Clone)]
+pub struct BoxContainer {
+  pub width: i64,
+  pub depth: i64,
+  pub height: i64,
+  // Payload limit. Boxes that would exceed it are not placed in this container.
+  pub max_weight: Option<i64>
+}
+
+impl BoxContainer {
+  pub fn new(width: i64, depth: i64, height: i64) -> BoxContainer {
+    BoxContainer { width, depth, height, max_weight: None }
+  }
+
+  pub fn volume(&self) -> i64 {
+    self.width * self.depth * self.height
+  }
+}
+
+impl std::fmt::Debug for BoxContainer {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(fmt, "Box container {}x{}x{}", self.width, self.depth, self.height)?;
+    if let Some(max_weight) = self.max_weight {
+      write!(fmt, " (max weight: {})", max_weight)?;
+    }
+    Ok(())
+  }
+}
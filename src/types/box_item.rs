@@ -0,0 +1,139 @@
+use super::shape::Shape;
+use crate::genetic_algorithm::Evolvable;
+use crate::math;
+use rand::seq::SliceRandom;
+
+// Orientations are numbered as "tilt * 2 + turn", where turn swaps the width and depth
+// (turned 90 degrees around the vertical axis) and tilt picks the side facing up:
+// 0 = upright, 1 = laid on its front (depth becomes the height), 2 = laid on its side (width becomes the height).
+static ORIENTATION_COUNT: i64 = 6;
+
+#[derive(Clone)]
+pub struct BoxItem {
+  pub width: i64,
+  pub depth: i64,
+  pub height: i64,
+  pub benefit: i64,
+  // Allows turning the box around the vertical axis.
+  pub rotatable: bool,
+  // Allows laying the box on any side.
+  pub tiltable: bool,
+  // Number of identical copies of this box.
+  pub quantity: i64,
+  // Every copy must be placed for a layout to be feasible.
+  pub required: bool,
+  // Counts towards the container's weight limit.
+  pub weight: i64,
+  // Footprint (width x depth) of the box in each orientation.
+  footprints: Vec<Shape>
+}
+
+impl BoxItem {
+  pub fn new(width: i64, depth: i64, height: i64, benefit: i64) -> BoxItem {
+    let mut item = BoxItem {
+      width,
+      depth,
+      height,
+      benefit,
+      rotatable: false,
+      tiltable: false,
+      quantity: 1,
+      required: false,
+      weight: 0,
+      footprints: Vec::<Shape>::new()
+    };
+    item.footprints = (0..ORIENTATION_COUNT).map(|orientation| {
+      let (width, depth, _) = item.size(orientation);
+      Shape::rectangle(width, depth)
+    }).collect();
+    item
+  }
+
+  // Width, depth and height of the box in the given orientation.
+  pub fn size(&self, orientation: i64) -> (i64, i64, i64) {
+    let (width, depth, height) = match orientation / 2 {
+      0 => (self.width, self.depth, self.height),
+      1 => (self.width, self.height, self.depth),
+      _ => (self.depth, self.height, self.width)
+    };
+    if orientation % 2 == 0 { (width, depth, height) } else { (depth, width, height) }
+  }
+
+  // The box seen from above, as placed on a layer.
+  pub fn footprint(&self, orientation: i64) -> &Shape {
+    &self.footprints[orientation as usize]
+  }
+
+  pub fn volume(&self) -> i64 {
+    self.width * self.depth * self.height
+  }
+
+  // Orientations allowed for this box, skipping the ones that have the same size as another.
+  pub fn orientations(&self) -> Vec<i64> {
+    let count = if self.tiltable { ORIENTATION_COUNT } else if self.rotatable { 2 } else { 1 };
+    let mut orientations = Vec::<i64>::new();
+    for orientation in 0..count {
+      if orientations.iter().any(|&other| self.size(other) == self.size(orientation)) { continue; }
+      orientations.push(orientation);
+    }
+    orientations
+  }
+}
+
+impl Evolvable for BoxItem {
+  fn quantity(&self) -> i64 {
+    self.quantity
+  }
+
+  fn benefit(&self) -> i64 {
+    self.benefit
+  }
+
+  fn required(&self) -> bool {
+    self.required
+  }
+
+  fn reorientable(&self) -> bool {
+    self.rotatable || self.tiltable
+  }
+
+  fn random_orientation(&self) -> i64 {
+    *self.orientations().choose(&mut math::rng()).unwrap_or(&0)
+  }
+
+  fn describe_orientation(orientation: i64) -> String {
+    let tilt = match orientation / 2 {
+      0 => "",
+      1 => "F",
+      _ => "S"
+    };
+    let turn = if orientation % 2 == 0 { "" } else { "R" };
+    format!("{}{}", tilt, turn)
+  }
+
+  fn orientation_legend() -> &'static str {
+    "F = laid on its front, S = laid on its side, R = turned 90 degrees around the vertical axis"
+  }
+}
+
+impl std::fmt::Debug for BoxItem {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(fmt, "Box {}x{}x{} (benefit: {})", self.width, self.depth, self.height, self.benefit)?;
+    if self.quantity > 1 {
+      write!(fmt, " x{}", self.quantity)?;
+    }
+    if self.rotatable {
+      write!(fmt, " rotatable")?;
+    }
+    if self.tiltable {
+      write!(fmt, " tiltable")?;
+    }
+    if self.required {
+      write!(fmt, " required")?;
+    }
+    if self.weight > 0 {
+      write!(fmt, " weight={}", self.weight)?;
+    }
+    Ok(())
+  }
+}
@@ -1,20 +1,60 @@
-#[derive(Copy, Clone)]
+use super::rect::Rect;
+
+#[derive(Clone)]
 pub struct Container {
   pub width: i64,
-  pub height: i64
+  pub height: i64,
+  // Regions where items can't be placed (defects, dividers, etc).
+  pub obstacles: Vec<Rect>,
+  // Cells that are not part of the container, for containers that aren't rectangular.
+  pub outside: Vec<Rect>,
+  // Payload limit. Items that would exceed it are not placed in this container.
+  pub max_weight: Option<i64>
 }
 
 impl Container {
   pub fn new(w: i64, h: i64) -> Container {
     Container {
       width: w,
-      height: h
+      height: h,
+      obstacles: Vec::<Rect>::new(),
+      outside: Vec::<Rect>::new(),
+      max_weight: None
     }
   }
+
+  // Builds an irregular container from a cell mask (true for usable cells).
+  pub fn from_mask(mask: &[Vec<bool>]) -> Container {
+    let height = mask.len() as i64;
+    let width = mask.iter().map(|row| row.len()).max().unwrap_or(0) as i64;
+    let mut container = Container::new(width, height);
+
+    let unusable: Vec<Vec<bool>> = mask.iter().map(|row| {
+      (0..width as usize).map(|col| col >= row.len() || !row[col]).collect()
+    }).collect();
+
+    container.outside = Rect::cover(&unusable);
+    container
+  }
+
+  // Everything items can't be placed on.
+  pub fn blocked(&self) -> Vec<Rect> {
+    self.obstacles.iter().chain(self.outside.iter()).cloned().collect()
+  }
 }
 
 impl std::fmt::Debug for Container {
   fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
-    write!(fmt, "Container {}x{}", self.width, self.height)
+    write!(fmt, "Container {}x{}", self.width, self.height)?;
+    if !self.outside.is_empty() {
+      write!(fmt, " (irregular shape)")?;
+    }
+    if let Some(max_weight) = self.max_weight {
+      write!(fmt, " (max weight: {})", max_weight)?;
+    }
+    for obstacle in &self.obstacles {
+      write!(fmt, " (obstacle: {:?})", obstacle)?;
+    }
+    Ok(())
   }
 }
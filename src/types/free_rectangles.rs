@@ -0,0 +1,276 @@
+use super::rect::Rect;
+use super::shape::Shape;
+use crate::space::FreeSpace;
+
+// Keeps the list of maximal free rectangles (MaxRects) instead of every cell,
+// so the cost of placing an item depends on how many items were placed,
+// and not on the container area.
+pub struct FreeRectangles {
+  pub width: i64,
+  pub height: i64,
+  free: Vec<Rect>,
+  placed: Vec<Rect>,
+  // State restored by every reset (i.e. with the blocked rectangles already placed).
+  initial_free: Vec<Rect>,
+  initial_placed: Vec<Rect>
+}
+
+// Length of the overlap between [a1, a2) and [b1, b2).
+fn overlap(a1: i64, a2: i64, b1: i64, b2: i64) -> i64 {
+  std::cmp::max(0, std::cmp::min(a2, b2) - std::cmp::max(a1, b1))
+}
+
+impl FreeRectangles {
+  pub fn new(width: i64, height: i64, blocked: &[Rect]) -> FreeRectangles {
+    let mut free_rectangles = FreeRectangles {
+      width,
+      height,
+      free: vec![Rect::new(0, 0, width, height)],
+      placed: Vec::<Rect>::new(),
+      initial_free: Vec::<Rect>::new(),
+      initial_placed: Vec::<Rect>::new()
+    };
+
+    for rect in blocked {
+      free_rectangles.fill(rect.row, rect.col, rect.width, rect.height);
+    }
+    free_rectangles.initial_free = free_rectangles.free.clone();
+    free_rectangles.initial_placed = free_rectangles.placed.clone();
+    free_rectangles
+  }
+
+  // Removes free rectangles that are inside another one.
+  fn prune(&mut self) {
+    let mut i = 0;
+    while i < self.free.len() {
+      let contained = (0..self.free.len()).any(|j| {
+        // For duplicated rectangles only keep the first one.
+        j != i && self.free[j].contains(&self.free[i]) && (self.free[j] != self.free[i] || j < i)
+      });
+
+      if contained {
+        self.free.swap_remove(i);
+      } else {
+        i += 1;
+      }
+    }
+  }
+}
+
+impl FreeSpace for FreeRectangles {
+  fn reset(&mut self) {
+    self.free.clone_from(&self.initial_free);
+    self.placed.clone_from(&self.initial_placed);
+  }
+
+  fn size(&self) -> (i64, i64) {
+    (self.width, self.height)
+  }
+
+  fn fits(&self, row: i64, col: i64, width: i64, height: i64) -> bool {
+    let rect = Rect::new(row, col, width, height);
+    self.free.iter().any(|free| free.contains(&rect))
+  }
+
+  fn fill(&mut self, row: i64, col: i64, width: i64, height: i64) {
+    let used = Rect::new(row, col, width, height);
+    let mut split = Vec::<Rect>::new();
+
+    self.free.retain(|free| {
+      if !free.intersects(&used) {
+        return true;
+      }
+
+      // Keep the parts of the free rectangle that lie above, below, left and right of the used one.
+      if used.row > free.row {
+        split.push(Rect::new(free.row, free.col, free.width, used.row - free.row));
+      }
+      if used.bottom() < free.bottom() {
+        split.push(Rect::new(used.bottom(), free.col, free.width, free.bottom() - used.bottom()));
+      }
+      if used.col > free.col {
+        split.push(Rect::new(free.row, free.col, used.col - free.col, free.height));
+      }
+      if used.right() < free.right() {
+        split.push(Rect::new(free.row, used.right(), free.right() - used.right(), free.height));
+      }
+      false
+    });
+
+    self.free.append(&mut split);
+    self.prune();
+    self.placed.push(used);
+  }
+
+  fn count_free(&self) -> i64 {
+    // Placed rectangles may overlap (e.g. obstacles, or spacing margins), so the container is split
+    // into bands of rows where no rectangle begins or ends, and the covered cells of each band are merged.
+    let mut rows: Vec<i64> = self.placed.iter().flat_map(|rect| vec![rect.row, rect.bottom()]).collect();
+    rows.sort_unstable();
+    rows.dedup();
+
+    let used: i64 = rows.windows(2).map(|band| {
+      Rect::covered_cells_in_row(self.placed.iter(), band[0], 0, self.width) * (band[1] - band[0])
+    }).sum();
+    (self.width * self.height) - used
+  }
+
+  // Only positions where a rectangle of the shape touches a corner of a free rectangle are tried.
+  // For rectangular items, the top-left-most (and bottom-left-most) position where it fits is always one of them.
+  fn visit_positions(&self, shape: &Shape, bottom_up: bool, visit: &mut dyn FnMut(i64, i64) -> bool) {
+    self.visit_spaced_positions(shape, bottom_up, 0, visit);
+  }
+
+  // The corners touch the container border or the items around (or their spacing margin), so
+  // with spacing each corner is also tried moved inwards by the spacing, across and along the free rectangle.
+  fn visit_spaced_positions(&self, shape: &Shape, bottom_up: bool, spacing: i64, visit: &mut dyn FnMut(i64, i64) -> bool) {
+    let mut positions = Vec::<(i64, i64)>::new();
+    let mut moved = Vec::<(i64, i64)>::new();
+
+    for free in &self.free {
+      for rect in &shape.rects {
+        if free.width < rect.width || free.height < rect.height { continue; }
+        // Each side, with the direction that goes inwards.
+        let rows = [(free.row - rect.row, 1), (free.bottom() - rect.bottom(), -1)];
+        let cols = [(free.col - rect.col, 1), (free.right() - rect.right(), -1)];
+        for &(row, row_inwards) in &rows {
+          for &(col, col_inwards) in &cols {
+            positions.push((row, col));
+            if spacing > 0 {
+              moved.push((row + spacing * row_inwards, col));
+              moved.push((row, col + spacing * col_inwards));
+              moved.push((row + spacing * row_inwards, col + spacing * col_inwards));
+            }
+          }
+        }
+      }
+    }
+
+    // With a single rectangle the shape always fits at a corner, otherwise the other rectangles have to be checked.
+    if !shape.is_rectangle() {
+      positions.retain(|&(row, col)| self.fits_shape(row, col, shape));
+    }
+    moved.retain(|&(row, col)| self.fits_shape(row, col, shape));
+    positions.append(&mut moved);
+
+    if bottom_up {
+      positions.sort_by_key(|&(row, col)| (-row, col));
+    } else {
+      positions.sort();
+    }
+    positions.dedup();
+
+    for (row, col) in positions {
+      if !visit(row, col) {
+        return;
+      }
+    }
+  }
+
+  fn contact_perimeter(&self, row: i64, col: i64, width: i64, height: i64) -> i64 {
+    let mut contact = 0;
+
+    if row == 0 { contact += width; }
+    if row + height == self.height { contact += width; }
+    if col == 0 { contact += height; }
+    if col + width == self.width { contact += height; }
+
+    for placed in &self.placed {
+      if placed.bottom() == row || placed.row == row + height {
+        contact += overlap(col, col + width, placed.col, placed.right());
+      }
+      if placed.right() == col || placed.col == col + width {
+        contact += overlap(row, row + height, placed.row, placed.bottom());
+      }
+    }
+
+    contact
+  }
+
+  fn trapped_cells_above(&self, row: i64, col: i64, width: i64) -> i64 {
+    let above: Vec<&Rect> = self.placed.iter()
+                                       .filter(|placed| placed.bottom() <= row && overlap(col, col + width, placed.col, placed.right()) > 0)
+                                       .collect();
+
+    // Split the item's top edge where rectangles above begin or end, and measure each piece.
+    let mut cuts: Vec<i64> = vec![col, col + width];
+    for placed in &above {
+      cuts.push(std::cmp::max(placed.col, col));
+      cuts.push(std::cmp::min(placed.right(), col + width));
+    }
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    let mut trapped = 0;
+    for piece in cuts.windows(2) {
+      let ceiling: i64 = above.iter()
+                              .filter(|placed| placed.col <= piece[0] && piece[1] <= placed.right())
+                              .map(|placed| placed.bottom())
+                              .max()
+                              .unwrap_or(0);
+      trapped += (row - ceiling) * (piece[1] - piece[0]);
+    }
+
+    trapped
+  }
+
+  fn filled_cells_below(&self, row: i64, col: i64, width: i64) -> i64 {
+    let below = row + 1;
+    if below >= self.height {
+      return width;
+    }
+
+    // Placed rectangles may overlap (e.g. spacing margins).
+    Rect::covered_cells_in_row(self.placed.iter(), below, col, width)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sorted_free(free_rectangles: &FreeRectangles) -> Vec<(i64, i64, i64, i64)> {
+    let mut free: Vec<(i64, i64, i64, i64)> = free_rectangles.free.iter().map(|rect| (rect.row, rect.col, rect.width, rect.height)).collect();
+    free.sort_unstable();
+    free
+  }
+
+  #[test]
+  fn fill_splits_into_maximal_rectangles() {
+    let mut free_rectangles = FreeRectangles::new(10, 8, &[]);
+    free_rectangles.fill(3, 4, 2, 2);
+
+    // Above, left, right and below the filled rectangle, each one as large as possible.
+    assert_eq!(sorted_free(&free_rectangles), vec![(0, 0, 4, 8), (0, 0, 10, 3), (0, 6, 4, 8), (5, 0, 10, 3)]);
+    assert!(free_rectangles.fits(0, 0, 10, 3));
+    assert!(!free_rectangles.fits(2, 3, 2, 2));
+    assert!(free_rectangles.fits(2, 6, 4, 6));
+  }
+
+  #[test]
+  fn prune_removes_contained_rectangles() {
+    let mut free_rectangles = FreeRectangles::new(10, 8, &[]);
+    free_rectangles.fill(0, 0, 10, 3);
+    free_rectangles.fill(3, 0, 4, 5);
+
+    assert_eq!(sorted_free(&free_rectangles), vec![(3, 4, 6, 5)]);
+    let free = &free_rectangles.free;
+    assert!((0..free.len()).all(|i| (0..free.len()).all(|j| i == j || !free[j].contains(&free[i]))));
+  }
+
+  #[test]
+  fn count_free_with_obstacles() {
+    // The obstacles overlap, and the shared cells count once.
+    let obstacles = vec![Rect::new(0, 0, 4, 4), Rect::new(2, 2, 4, 4)];
+    let mut free_rectangles = FreeRectangles::new(10, 8, &obstacles);
+    assert_eq!(free_rectangles.count_free(), 80 - 28);
+    assert!(!free_rectangles.fits(3, 3, 1, 1));
+
+    free_rectangles.fill(6, 8, 2, 2);
+    assert_eq!(free_rectangles.count_free(), 80 - 32);
+
+    free_rectangles.reset();
+    assert_eq!(free_rectangles.count_free(), 80 - 28);
+    assert!(free_rectangles.fits(6, 8, 2, 2));
+  }
+}
@@ -0,0 +1,203 @@
+use super::rect::Rect;
+use super::shape::Shape;
+use crate::space::FreeSpace;
+
+// Bit-packed occupancy grid. Each row is stored as a sequence of u64 words,
+// so a whole row span can be checked or filled with a few mask operations.
+pub struct OccupancyGrid {
+  pub width: i64,
+  pub height: i64,
+  words_per_row: usize,
+  words: Vec<u64>,
+  // State restored by every reset (i.e. with the blocked cells already filled).
+  initial_words: Vec<u64>
+}
+
+// Mask of the bits in [from, to) inside a single word (0 <= from < to <= 64).
+fn word_mask(from: i64, to: i64) -> u64 {
+  let high: u64 = if to == 64 { !0 } else { (1u64 << to) - 1 };
+  let low: u64 = (1u64 << from) - 1;
+  high & !low
+}
+
+impl OccupancyGrid {
+  pub fn new(width: i64, height: i64, blocked: &[Rect]) -> OccupancyGrid {
+    let words_per_row: usize = ((width + 63) / 64) as usize;
+    let mut grid = OccupancyGrid {
+      width,
+      height,
+      words_per_row,
+      words: vec![0; words_per_row * height as usize],
+      initial_words: Vec::<u64>::new()
+    };
+
+    for rect in blocked {
+      grid.fill(rect.row, rect.col, rect.width, rect.height);
+    }
+    grid.initial_words = grid.words.clone();
+    grid
+  }
+
+  pub fn is_filled(&self, row: i64, col: i64) -> bool {
+    let word = self.words[self.word_index(row, col)];
+    word & (1u64 << (col % 64)) != 0
+  }
+
+  // Cells outside the grid count as blocked.
+  fn is_blocked(&self, row: i64, col: i64) -> bool {
+    if row < 0 || col < 0 || row >= self.height || col >= self.width {
+      return true;
+    }
+
+    self.is_filled(row, col)
+  }
+
+  fn span_is_empty(&self, row: i64, col: i64, width: i64) -> bool {
+    let mut j = col;
+    while j < col + width {
+      let word_end: i64 = std::cmp::min((j / 64 + 1) * 64, col + width);
+      let word = self.words[self.word_index(row, j)];
+      if word & word_mask(j % 64, word_end - (j / 64) * 64) != 0 {
+        return false;
+      }
+      j = word_end;
+    }
+    true
+  }
+
+  fn word_index(&self, row: i64, col: i64) -> usize {
+    (row as usize * self.words_per_row) + (col / 64) as usize
+  }
+}
+
+impl FreeSpace for OccupancyGrid {
+  fn reset(&mut self) {
+    self.words.copy_from_slice(&self.initial_words);
+  }
+
+  fn size(&self) -> (i64, i64) {
+    (self.width, self.height)
+  }
+
+  fn fits(&self, row: i64, col: i64, width: i64, height: i64) -> bool {
+    if row < 0 || col < 0 { return false; }
+    if (row + height) > self.height { return false; }
+    if (col + width) > self.width { return false; }
+
+    for i in row..(row + height) {
+      if !self.span_is_empty(i, col, width) {
+        return false;
+      }
+    }
+
+    true
+  }
+
+  fn fill(&mut self, row: i64, col: i64, width: i64, height: i64) {
+    for i in row..(row + height) {
+      let mut j = col;
+      while j < col + width {
+        let word_end: i64 = std::cmp::min((j / 64 + 1) * 64, col + width);
+        let idx = self.word_index(i, j);
+        self.words[idx] |= word_mask(j % 64, word_end - (j / 64) * 64);
+        j = word_end;
+      }
+    }
+  }
+
+  fn count_free(&self) -> i64 {
+    let filled: i64 = self.words.iter().map(|word| word.count_ones() as i64).sum();
+    (self.width * self.height) - filled
+  }
+
+  // Every cell is a candidate.
+  fn visit_positions(&self, shape: &Shape, bottom_up: bool, visit: &mut dyn FnMut(i64, i64) -> bool) {
+    for i in 0..self.height {
+      let row = if bottom_up { self.height - 1 - i } else { i };
+      for col in 0..self.width {
+        // Rectangles are the most common items, and don't need to go through their list of rectangles.
+        let fits = if shape.is_rectangle() { self.fits(row, col, shape.width, shape.height) } else { self.fits_shape(row, col, shape) };
+        if fits && !visit(row, col) {
+          return;
+        }
+      }
+    }
+  }
+
+  fn contact_perimeter(&self, row: i64, col: i64, width: i64, height: i64) -> i64 {
+    let mut contact = 0;
+
+    for j in col..(col + width) {
+      if self.is_blocked(row - 1, j) { contact += 1; }
+      if self.is_blocked(row + height, j) { contact += 1; }
+    }
+
+    for i in row..(row + height) {
+      if self.is_blocked(i, col - 1) { contact += 1; }
+      if self.is_blocked(i, col + width) { contact += 1; }
+    }
+
+    contact
+  }
+
+  fn trapped_cells_above(&self, row: i64, col: i64, width: i64) -> i64 {
+    let mut trapped = 0;
+
+    for j in col..(col + width) {
+      let mut i = row - 1;
+      while !self.is_blocked(i, j) {
+        trapped += 1;
+        i -= 1;
+      }
+    }
+
+    trapped
+  }
+
+  fn filled_cells_below(&self, row: i64, col: i64, width: i64) -> i64 {
+    (col..(col + width)).filter(|&j| self.is_blocked(row + 1, j)).count() as i64
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn spans_across_words() {
+    // Three words per row. The rectangle covers the end of the first word and the start of the second one.
+    let mut grid = OccupancyGrid::new(130, 4, &[]);
+    assert!(grid.fits(1, 60, 10, 2));
+    grid.fill(1, 60, 10, 2);
+
+    assert!(!grid.fits(1, 60, 10, 2));
+    assert!(!grid.fits(0, 69, 1, 2));
+    assert!(!grid.fits(2, 55, 6, 1));
+    assert!(grid.fits(1, 50, 10, 2));
+    assert!(grid.fits(1, 70, 60, 2));
+    assert!(grid.is_filled(2, 63) && grid.is_filled(2, 64) && !grid.is_filled(2, 70));
+    assert_eq!(grid.count_free(), 130 * 4 - 20);
+  }
+
+  #[test]
+  fn fits_only_inside() {
+    let grid = OccupancyGrid::new(130, 4, &[]);
+    assert!(grid.fits(0, 0, 130, 4));
+    assert!(!grid.fits(0, 1, 130, 1));
+    assert!(!grid.fits(1, 0, 1, 4));
+    assert!(!grid.fits(-1, 0, 1, 1));
+  }
+
+  #[test]
+  fn reset_keeps_blocked_cells() {
+    let mut grid = OccupancyGrid::new(130, 4, &[Rect::new(0, 63, 2, 4)]);
+    grid.fill(0, 0, 130, 1);
+    grid.fill(3, 100, 30, 1);
+    grid.reset();
+
+    assert_eq!(grid.count_free(), 130 * 4 - 8);
+    assert!(!grid.fits(2, 64, 1, 1));
+    assert!(grid.fits(0, 0, 63, 4));
+    assert!(grid.fits(0, 65, 65, 4));
+  }
+}
@@ -1,31 +1,206 @@
+use super::shape::Shape;
+use crate::genetic_algorithm::Evolvable;
+use crate::math;
 use rand::Rng;
+use rand::seq::SliceRandom;
 
-#[derive(Copy, Clone)]
+// Position of an item that is already placed, and isn't moved by the genetic algorithm.
+#[derive(Clone, Copy)]
+pub struct FixedPosition {
+  pub container_idx: usize,
+  pub row: i64,
+  pub col: i64
+}
+
+#[derive(Clone)]
 pub struct Item {
   pub width: i64,
   pub height: i64,
-  pub benefit: i64
+  pub benefit: i64,
+  // Allows quarter turns.
+  pub rotatable: bool,
+  // Allows flipping the item horizontally. Only makes a difference for non-rectangular items.
+  pub mirrorable: bool,
+  // Number of identical copies of this item.
+  pub quantity: i64,
+  // Every copy must be placed for a layout to be feasible.
+  pub required: bool,
+  // Minimum gap (in cells) kept between this item and any other item, e.g. the blade kerf.
+  pub spacing: i64,
+  // Counts towards the container's weight limit.
+  pub weight: i64,
+  // Set for items that are already placed (with their original orientation).
+  pub fixed: Option<FixedPosition>,
+  // The item's shape in each of the 8 orientations (see Shape::oriented).
+  shapes: Vec<Shape>,
+  // For each orientation, the first orientation that covers the same cells.
+  same_as: Vec<i64>
 }
 
 impl Item {
   pub fn new(w: i64, h: i64, b: i64) -> Item {
-    Item {
+    let mut item = Item {
       width: w,
       height: h,
-      benefit: b
+      benefit: b,
+      rotatable: false,
+      mirrorable: false,
+      quantity: 1,
+      required: false,
+      spacing: 0,
+      weight: 0,
+      fixed: None,
+      shapes: Vec::<Shape>::new(),
+      same_as: Vec::<i64>::new()
+    };
+    item.set_shape(Shape::rectangle(w, h));
+    item
+  }
+
+  // A max_weight of 0 makes weightless items.
+  pub fn make_random(w: i64, h: i64, benefit: i64, rotatable: bool, quantity: i64, max_weight: i64) -> Item {
+    let rand_w = math::rng().gen_range(1..w);
+    let rand_h = math::rng().gen_range(1..h);
+    let rand_benefit = math::rng().gen_range(0..benefit);
+    let mut item = Item::new(rand_w, rand_h, rand_benefit);
+    item.rotatable = rotatable;
+    item.quantity = math::rng().gen_range(1..=quantity);
+    if max_weight > 0 {
+      item.weight = math::rng().gen_range(1..=max_weight);
     }
+    item
+  }
+
+  pub fn set_shape(&mut self, shape: Shape) {
+    self.width = shape.width;
+    self.height = shape.height;
+    self.shapes = (0..8).map(|orientation| shape.oriented(orientation)).collect();
+    self.same_as = (0..8).map(|orientation| {
+      (0..orientation).find(|&other| self.shapes[other as usize].same_cells(&self.shapes[orientation as usize])).unwrap_or(orientation)
+    }).collect();
   }
 
-  pub fn make_random(w: i64, h: i64, benefit: i64) -> Item {
-    let rand_w = rand::thread_rng().gen_range(1..w);
-    let rand_h = rand::thread_rng().gen_range(1..h);
-    let rand_benefit = rand::thread_rng().gen_range(0..benefit);
-    Item::new(rand_w, rand_h, rand_benefit)
+  pub fn shape(&self, orientation: i64) -> &Shape {
+    &self.shapes[orientation as usize]
+  }
+
+  // Orientations allowed for this item, skipping the ones that cover the same cells as another.
+  pub fn orientations(&self) -> Vec<i64> {
+    let mut orientations = Vec::<i64>::new();
+    for orientation in 0..8 {
+      if !self.rotatable && orientation & 3 != 0 { continue; }
+      if !self.mirrorable && orientation & 4 != 0 { continue; }
+      if orientations.iter().any(|&other| self.same_as[other as usize] == self.same_as[orientation as usize]) { continue; }
+      orientations.push(orientation);
+    }
+    orientations
+  }
+}
+
+impl Evolvable for Item {
+  fn quantity(&self) -> i64 {
+    self.quantity
+  }
+
+  fn benefit(&self) -> i64 {
+    self.benefit
+  }
+
+  fn required(&self) -> bool {
+    self.required
+  }
+
+  fn evolved_quantity(&self) -> i64 {
+    if self.fixed.is_some() { 0 } else { self.quantity }
+  }
+
+  fn reorientable(&self) -> bool {
+    self.rotatable || self.mirrorable
+  }
+
+  fn random_orientation(&self) -> i64 {
+    *self.orientations().choose(&mut math::rng()).unwrap_or(&0)
+  }
+
+  fn describe_orientation(orientation: i64) -> String {
+    let mut description = match orientation & 3 {
+      0 => String::new(),
+      1 => "R".to_string(),
+      turns => format!("R{}", turns)
+    };
+    if orientation & 4 != 0 {
+      description.push('M');
+    }
+    description
+  }
+
+  fn orientation_legend() -> &'static str {
+    "R = turned 90 degrees clockwise, R2/R3 = turned twice/three times, M = mirrored"
   }
 }
 
 impl std::fmt::Debug for Item {
   fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
-    write!(fmt, "Item {}x{} (benefit: {})", self.width, self.height, self.benefit)
+    write!(fmt, "Item {}x{} (benefit: {})", self.width, self.height, self.benefit)?;
+    if !self.shape(0).is_rectangle() {
+      write!(fmt, " shape={}", self.shape(0).describe())?;
+    }
+    if self.quantity > 1 {
+      write!(fmt, " x{}", self.quantity)?;
+    }
+    if self.rotatable {
+      write!(fmt, " rotatable")?;
+    }
+    if self.mirrorable {
+      write!(fmt, " mirrorable")?;
+    }
+    if self.required {
+      write!(fmt, " required")?;
+    }
+    if self.weight > 0 {
+      write!(fmt, " weight={}", self.weight)?;
+    }
+    if self.spacing > 0 {
+      write!(fmt, " spacing={}", self.spacing)?;
+    }
+    if let Some(fixed) = self.fixed {
+      write!(fmt, " fixed at ({}, {}) in container {}", fixed.row, fixed.col, fixed.container_idx + 1)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn item(description: &str) -> Item {
+    let cells: Vec<Vec<bool>> = description.split('/').map(|row| row.chars().map(|cell| cell == '#').collect()).collect();
+    let mut item = Item::new(1, 1, 1);
+    item.set_shape(Shape::from_cells(&cells));
+    item.rotatable = true;
+    item.mirrorable = true;
+    item
+  }
+
+  #[test]
+  fn symmetric_orientations_are_skipped() {
+    // Every orientation is different.
+    assert_eq!(item("#./#./##").orientations(), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+    // Mirroring gives the same cells.
+    assert_eq!(item("###/.#.").orientations(), vec![0, 1, 2, 3]);
+    // Half turns give the same cells.
+    assert_eq!(item("##./.##").orientations(), vec![0, 1, 4, 5]);
+    assert_eq!(item("###/###").orientations(), vec![0, 1]);
+    assert_eq!(item(".#./###/.#.").orientations(), vec![0]);
+  }
+
+  #[test]
+  fn orientations_follow_the_flags() {
+    let mut l_shape = item("#./#./##");
+    l_shape.mirrorable = false;
+    assert_eq!(l_shape.orientations(), vec![0, 1, 2, 3]);
+    l_shape.rotatable = false;
+    assert_eq!(l_shape.orientations(), vec![0]);
   }
 }
@@ -1,3 +1,9 @@
 pub mod container;
 pub mod item;
 pub mod stats;
+pub mod grid;
+pub mod rect;
+pub mod free_rectangles;
+pub mod shape;
+pub mod box_item;
+pub mod box_container;
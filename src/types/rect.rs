@@ -0,0 +1,131 @@
+#[derive(Copy, Clone, PartialEq)]
+pub struct Rect {
+  pub row: i64,
+  pub col: i64,
+  pub width: i64,
+  pub height: i64
+}
+
+impl Rect {
+  pub fn new(row: i64, col: i64, width: i64, height: i64) -> Rect {
+    Rect { row, col, width, height }
+  }
+
+  pub fn bottom(&self) -> i64 {
+    self.row + self.height
+  }
+
+  pub fn right(&self) -> i64 {
+    self.col + self.width
+  }
+
+  pub fn area(&self) -> i64 {
+    self.width * self.height
+  }
+
+  pub fn intersects(&self, other: &Rect) -> bool {
+    self.row < other.bottom() && other.row < self.bottom() &&
+    self.col < other.right() && other.col < self.right()
+  }
+
+  pub fn contains(&self, other: &Rect) -> bool {
+    self.row <= other.row && other.bottom() <= self.bottom() &&
+    self.col <= other.col && other.right() <= self.right()
+  }
+
+  pub fn contains_cell(&self, row: i64, col: i64) -> bool {
+    self.row <= row && row < self.bottom() && self.col <= col && col < self.right()
+  }
+
+  // The rectangle with `margin` cells added on every side.
+  pub fn grown(&self, margin: i64) -> Rect {
+    Rect::new(self.row - margin, self.col - margin, self.width + 2 * margin, self.height + 2 * margin)
+  }
+
+  // The part of the rectangle that lies inside `bounds`, if any.
+  pub fn clipped(&self, bounds: &Rect) -> Option<Rect> {
+    let row = std::cmp::max(self.row, bounds.row);
+    let col = std::cmp::max(self.col, bounds.col);
+    let bottom = std::cmp::min(self.bottom(), bounds.bottom());
+    let right = std::cmp::min(self.right(), bounds.right());
+    if row < bottom && col < right {
+      Some(Rect::new(row, col, right - col, bottom - row))
+    } else {
+      None
+    }
+  }
+
+  pub fn intersection_area(&self, other: &Rect) -> i64 {
+    let width = std::cmp::min(self.right(), other.right()) - std::cmp::max(self.col, other.col);
+    let height = std::cmp::min(self.bottom(), other.bottom()) - std::cmp::max(self.row, other.row);
+    std::cmp::max(0, width) * std::cmp::max(0, height)
+  }
+
+  // Cells of `row` between `col` and `col + width` covered by any of the rectangles, which may overlap.
+  pub fn covered_cells_in_row<'a>(rects: impl Iterator<Item = &'a Rect>, row: i64, col: i64, width: i64) -> i64 {
+    let mut spans: Vec<(i64, i64)> = rects.filter(|rect| rect.row <= row && row < rect.bottom())
+                                          .map(|rect| (std::cmp::max(rect.col, col), std::cmp::min(rect.right(), col + width)))
+                                          .filter(|(start, end)| start < end)
+                                          .collect();
+    spans.sort_unstable();
+
+    let mut covered = 0;
+    let mut covered_until = col;
+    for (start, end) in spans {
+      let start = std::cmp::max(start, covered_until);
+      if end > start {
+        covered += end - start;
+        covered_until = end;
+      }
+    }
+    covered
+  }
+
+  // Groups the true cells into non-overlapping rectangles.
+  pub fn cover(cells: &[Vec<bool>]) -> Vec<Rect> {
+    let mut rects = Vec::<Rect>::new();
+
+    // Rectangles that reach the previous row, and can still grow downwards.
+    let mut open = Vec::<Rect>::new();
+
+    for (row, row_cells) in cells.iter().enumerate() {
+      let width = row_cells.len() as i64;
+      let mut next_open = Vec::<Rect>::new();
+
+      let mut col = 0;
+      while col < width {
+        if !row_cells[col as usize] {
+          col += 1;
+          continue;
+        }
+
+        let start = col;
+        while col < width && row_cells[col as usize] {
+          col += 1;
+        }
+
+        // Extend the rectangle above if it spans exactly the same columns.
+        match open.iter().position(|rect| rect.col == start && rect.width == col - start) {
+          Some(idx) => {
+            let mut rect = open.swap_remove(idx);
+            rect.height += 1;
+            next_open.push(rect);
+          },
+          None => next_open.push(Rect::new(row as i64, start, col - start, 1))
+        }
+      }
+
+      rects.append(&mut open);
+      open = next_open;
+    }
+
+    rects.append(&mut open);
+    rects
+  }
+}
+
+impl std::fmt::Debug for Rect {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(fmt, "Rect {}x{} at ({}, {})", self.width, self.height, self.row, self.col)
+  }
+}
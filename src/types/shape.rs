@@ -0,0 +1,141 @@
+use super::rect::Rect;
+
+// Cells covered by an item, stored as non-overlapping rectangles so large
+// rectangular items don't need a cell mask.
+#[derive(Clone)]
+pub struct Shape {
+  pub width: i64,
+  pub height: i64,
+  pub rects: Vec<Rect>
+}
+
+impl Shape {
+  pub fn rectangle(width: i64, height: i64) -> Shape {
+    Shape {
+      width,
+      height,
+      rects: vec![Rect::new(0, 0, width, height)]
+    }
+  }
+
+  // Cells are given row by row (true for covered cells).
+  pub fn from_cells(cells: &[Vec<bool>]) -> Shape {
+    Shape {
+      width: cells.iter().map(|row| row.len()).max().unwrap_or(0) as i64,
+      height: cells.len() as i64,
+      rects: Rect::cover(cells)
+    }
+  }
+
+  // True if the shape is a single rectangle filling its bounding box, so it can be handled as a plain rectangle.
+  pub fn is_rectangle(&self) -> bool {
+    self.rects.len() == 1 && self.rects[0] == Rect::new(0, 0, self.width, self.height)
+  }
+
+  pub fn area(&self) -> i64 {
+    self.rects.iter().map(|rect| rect.area()).sum()
+  }
+
+  pub fn contains_cell(&self, row: i64, col: i64) -> bool {
+    self.rects.iter().any(|rect| rect.contains_cell(row, col))
+  }
+
+  // Orientations go from 0 to 7. The lowest two bits are the number of clockwise quarter turns,
+  // and the third bit mirrors the shape horizontally (before turning it).
+  pub fn oriented(&self, orientation: i64) -> Shape {
+    let mut shape = self.clone();
+
+    if orientation & 4 != 0 {
+      for rect in shape.rects.iter_mut() {
+        rect.col = shape.width - rect.right();
+      }
+    }
+
+    for _ in 0..(orientation & 3) {
+      for rect in shape.rects.iter_mut() {
+        *rect = Rect::new(rect.col, shape.height - rect.bottom(), rect.height, rect.width);
+      }
+      std::mem::swap(&mut shape.width, &mut shape.height);
+    }
+
+    shape
+  }
+
+  // True if both shapes cover the same cells (even if the rectangles are split differently).
+  pub fn same_cells(&self, other: &Shape) -> bool {
+    if self.width != other.width || self.height != other.height || self.area() != other.area() {
+      return false;
+    }
+
+    // Rectangles don't overlap, so a rectangle is covered when its intersections add up to its area.
+    self.rects.iter().all(|rect| {
+      other.rects.iter().map(|other_rect| rect.intersection_area(other_rect)).sum::<i64>() == rect.area()
+    })
+  }
+
+  // Top edge of the shape, as horizontal segments (row is the topmost covered cell of each segment).
+  pub fn top_profile(&self) -> Vec<Rect> {
+    self.profile(|rects| rects.map(|rect| rect.row).min())
+  }
+
+  // Bottom edge of the shape, as horizontal segments (row is the lowest covered cell of each segment).
+  pub fn bottom_profile(&self) -> Vec<Rect> {
+    self.profile(|rects| rects.map(|rect| rect.bottom() - 1).max())
+  }
+
+  // Splits the shape into columns where rectangles begin or end, and picks a row for each one.
+  fn profile(&self, pick_row: impl Fn(&mut dyn Iterator<Item = &Rect>) -> Option<i64>) -> Vec<Rect> {
+    let mut cuts: Vec<i64> = self.rects.iter().flat_map(|rect| vec![rect.col, rect.right()]).collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    cuts.windows(2).filter_map(|piece| {
+      let mut covering = self.rects.iter().filter(|rect| rect.col <= piece[0] && piece[1] <= rect.right());
+      pick_row(&mut covering).map(|row| Rect::new(row, piece[0], piece[1] - piece[0], 1))
+    }).collect()
+  }
+
+  // Rows separated by '/', using '#' for covered cells (e.g. "###/.#./.#.").
+  pub fn describe(&self) -> String {
+    let rows: Vec<String> = (0..self.height).map(|row| {
+      (0..self.width).map(|col| if self.contains_cell(row, col) { '#' } else { '.' }).collect()
+    }).collect();
+    rows.join("/")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Same format as Shape::describe.
+  fn shape(description: &str) -> Shape {
+    let cells: Vec<Vec<bool>> = description.split('/').map(|row| row.chars().map(|cell| cell == '#').collect()).collect();
+    Shape::from_cells(&cells)
+  }
+
+  #[test]
+  fn eight_orientations() {
+    let l_shape = shape("#./#./##");
+    let expected = ["#./#./##", "###/#..", "##/.#/.#", "..#/###", ".#/.#/##", "#../###", "##/#./#.", "###/..#"];
+    for (orientation, description) in expected.iter().enumerate() {
+      let oriented = l_shape.oriented(orientation as i64);
+      assert_eq!(oriented.describe(), *description, "orientation {}", orientation);
+      assert_eq!(oriented.area(), 4);
+    }
+  }
+
+  #[test]
+  fn same_cells_ignores_how_rectangles_are_split() {
+    let split = Shape { width: 2, height: 2, rects: vec![Rect::new(0, 0, 1, 2), Rect::new(0, 1, 1, 2)] };
+    assert!(split.same_cells(&Shape::rectangle(2, 2)));
+    assert!(!shape("##/#.").same_cells(&shape("##/.#")));
+  }
+
+  #[test]
+  fn rectangles() {
+    assert!(Shape::rectangle(3, 2).is_rectangle());
+    assert!(shape("###/###").is_rectangle());
+    assert!(!shape("##/#.").is_rectangle());
+  }
+}
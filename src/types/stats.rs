@@ -1,44 +1,124 @@
-use super::item::Item;
+use crate::genetic_algorithm::Evolvable;
+use crate::gene;
+use crate::pareto::ParetoPoint;
 
+#[derive(Clone)]
 pub struct Stats {
   pub max_possible_score: i64,
   pub total_generations: i64,
   pub optimal_best_score: i64,
+  // Objective value of the optimal solution (see objective::Objective).
+  pub optimal_value: f64,
   pub optimal_hash: String,
   pub optimal_wasted: i64,
   pub optimal_found_gens: Vec<i64>,
-  pub optimal_solution: Vec<i64>
+  pub optimal_solution: Vec<i64>,
+  pub optimal_container_benefits: Vec<i64>,
+  pub optimal_container_weights: Vec<i64>,
+  pub container_max_weights: Vec<Option<i64>>,
+  pub optimal_missing_required: i64,
+  pub required_count: i64,
+  // Multi-objective mode: feasible solutions that no other solution found beats in both benefit and wasted room.
+  pub pareto_front: Vec<ParetoPoint>,
+  // Solutions whose score was found in the fitness cache, out of all the solutions looked up.
+  pub cache_hits: i64,
+  pub cache_lookups: i64,
+  describe_orientation: fn(i64) -> String,
+  orientation_legend: &'static str
 }
 
 impl Stats {
-  pub fn new(items: &Vec<Item>) -> Stats {
+  pub fn new<I: Evolvable>(items: &[I], container_max_weights: Vec<Option<i64>>) -> Stats {
+    // Placing every item also places every required item, so this is still the best possible score.
     let mut max_possible_score: i64 = 0;
+    let mut required_count: i64 = 0;
     for item in items {
-      max_possible_score += item.benefit;
+      max_possible_score += item.benefit() * item.quantity();
+      if item.required() {
+        required_count += item.quantity();
+      }
     }
 
     Stats {
-      max_possible_score: max_possible_score,
+      max_possible_score,
       total_generations: 0,
-      optimal_best_score: 0,
+      // Infeasible solutions have negative scores, so start below any of them.
+      optimal_best_score: i64::MIN,
+      optimal_value: f64::NEG_INFINITY,
       optimal_hash: String::new(),
       optimal_wasted: 0,
       optimal_found_gens: Vec::<i64>::new(),
-      optimal_solution: Vec::<i64>::new()
+      optimal_solution: Vec::<i64>::new(),
+      optimal_container_benefits: Vec::<i64>::new(),
+      optimal_container_weights: Vec::<i64>::new(),
+      container_max_weights,
+      optimal_missing_required: 0,
+      required_count,
+      pareto_front: Vec::<ParetoPoint>::new(),
+      cache_hits: 0,
+      cache_lookups: 0,
+      describe_orientation: I::describe_orientation,
+      orientation_legend: I::orientation_legend()
     }
   }
 
-  pub fn store_optimal_solution(&mut self, solution: &Vec<i64>) {
+  // A layout is feasible when every required item was placed.
+  pub fn is_feasible(&self) -> bool {
+    self.optimal_missing_required == 0
+  }
+
+  pub fn store_optimal_solution(&mut self, solution: &[i64]) {
     self.optimal_solution = solution.to_vec();
   }
 
+  // Takes the optimal solution (and everything known about it) from other stats, e.g. from an island.
+  pub fn copy_optimal(&mut self, other: &Stats) {
+    self.optimal_best_score = other.optimal_best_score;
+    self.optimal_value = other.optimal_value;
+    self.optimal_hash = other.optimal_hash.clone();
+    self.optimal_wasted = other.optimal_wasted;
+    self.optimal_solution = other.optimal_solution.clone();
+    self.optimal_container_benefits = other.optimal_container_benefits.clone();
+    self.optimal_container_weights = other.optimal_container_weights.clone();
+    self.optimal_missing_required = other.optimal_missing_required;
+  }
+
+  // E.g. "12/20, 7/unlimited".
+  fn describe_weights(&self) -> String {
+    let weights: Vec<String> = self.optimal_container_weights.iter().zip(self.container_max_weights.iter()).map(|(weight, max_weight)| {
+      match max_weight {
+        Some(max_weight) => format!("{}/{}", weight, max_weight),
+        None => format!("{}/unlimited", weight)
+      }
+    }).collect();
+    weights.join(", ")
+  }
+
   pub fn print(&self){
     println!("Max score assuming infinite container: {}", self.max_possible_score);
     println!("Total generations: {}", self.total_generations);
-    println!("Current optimal: {}", self.optimal_best_score);
+    if self.is_feasible() {
+      println!("Current optimal: {} | Objective value: {:.3}", self.optimal_best_score, self.optimal_value);
+    } else {
+      println!("No feasible layout found: {} of {} required items could not be placed.", self.optimal_missing_required, self.required_count);
+      println!("Current optimal (penalized): {} | Objective value: {:.3}", self.optimal_best_score, self.optimal_value);
+    }
     println!("Optimal ID: {}", self.optimal_hash);
     println!("Wasted room: {}", self.optimal_wasted);
+    println!("Benefit per container: {:?}", self.optimal_container_benefits);
+    if self.container_max_weights.iter().any(Option::is_some) || self.optimal_container_weights.iter().any(|&weight| weight > 0) {
+      println!("Weight per container (used/capacity): {}", self.describe_weights());
+    }
+    if self.cache_lookups > 0 {
+      println!("Fitness cache hits: {}/{} ({:.1}%)", self.cache_hits, self.cache_lookups, 100.0 * self.cache_hits as f64 / self.cache_lookups as f64);
+    }
     println!("Generations where local optimums were found: {:?}", self.optimal_found_gens);
-    println!("Best solution found: {:?}", self.optimal_solution);
+    println!("Best solution found ({}): {}", self.orientation_legend, gene::describe(&self.optimal_solution, self.describe_orientation));
+    if !self.pareto_front.is_empty() {
+      println!("Pareto front (benefit versus wasted room):");
+      for (idx, point) in self.pareto_front.iter().enumerate() {
+        println!("#{} Benefit: {} | Wasted room: {} | {}", idx + 1, point.benefit, point.wasted, gene::describe(&point.solution, self.describe_orientation));
+      }
+    }
   }
 }